@@ -1,18 +1,72 @@
+use crate::{span::Span, vm::bigint::BigInt};
+
 #[derive(Copy, Clone, Debug)]
 pub(crate) enum BinopKind {
     Plus,
     Minus,
     Times,
     Divide,
+    Modulo,
+    Equal,
+    NotEqual,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+    And,
+    Or,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum UnaryKind {
+    Negate,
+    Not,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum StmtKind {
+    Binding(String, Expr),
+    Assign(String, Expr),
+    // The value being indexed, the index and the new item
+    IndexAssign(Expr, Expr, Expr),
+    Print(Expr),
+    If(Expr, Vec<Stmt>, Option<Vec<Stmt>>),
+    While(Expr, Vec<Stmt>),
+    Function(String, Vec<String>, Vec<Stmt>),
+    // A bare return returns nil
+    Return(Option<Expr>),
+    Expr(Expr),
 }
 
 #[derive(Clone, Debug)]
-pub(crate) enum Stmt {
-    Binding(String, Expr)
+pub(crate) struct Stmt {
+    pub(crate) kind: StmtKind,
+    pub(crate) span: Span,
 }
 
 #[derive(Clone, Debug)]
-pub(crate) enum Expr {
+pub(crate) enum ExprKind {
     Number(i32),
+    BigNumber(BigInt),
+    Float(f64),
+    Bool(bool),
+    Nil,
+    String(String),
+    List(Vec<Expr>),
+    // The keys and values, in the order they were written
+    Map(Vec<(Expr, Expr)>),
+    Ident(String),
     Binop(BinopKind, Box<Expr>, Box<Expr>),
+    Unary(UnaryKind, Box<Expr>),
+    Call(Box<Expr>, Vec<Expr>),
+    Index(Box<Expr>, Box<Expr>),
+    // Either bound can be left out, to slice from the start or to the end
+    Slice(Box<Expr>, Option<Box<Expr>>, Option<Box<Expr>>),
+    Function(Vec<String>, Vec<Stmt>),
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Expr {
+    pub(crate) kind: ExprKind,
+    pub(crate) span: Span,
 }
@@ -0,0 +1,931 @@
+use std::{collections::HashSet, fmt, rc::Rc};
+
+use crate::{
+    ast::{BinopKind, Expr, ExprKind, Stmt, StmtKind, UnaryKind},
+    span::Span,
+    vm::{
+        function::Function,
+        instr::{BinopKind as InstrBinopKind, CompareKind, Instr, UnaryKind as InstrUnaryKind},
+        value::Value,
+    },
+};
+
+pub(crate) type Result<T = ()> = std::result::Result<T, ErrorKind>;
+
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    ReturnOutsideFunction(Span),
+}
+
+pub(crate) struct Compiler {
+    stmts: Vec<Stmt>,
+    instrs: Vec<Instr>,
+
+    // Where in the source each instruction came from, so runtime errors can say where they
+    // happened. Instructions take the span of the innermost statement or expression compiled.
+    spans: Vec<Span>,
+    span: Span,
+
+    // Whether the value of a final expression statement is left on the stack as the result of the
+    // whole program, rather than being discarded. Otherwise nil is left.
+    result: bool,
+
+    // The address the instructions will start at, as jumps are absolute
+    offset: usize,
+
+    // The functions being compiled, innermost last. The first is the top level, where bindings
+    // outside of any scope are globals.
+    functions: Vec<FunctionScope>,
+}
+
+#[derive(Default)]
+struct FunctionScope {
+    // Names declared in each scope of the function
+    scopes: Vec<HashSet<String>>,
+
+    // Locals of the enclosing functions which are used by the function
+    captures: Vec<String>,
+}
+
+impl Compiler {
+    pub(crate) fn new(stmts: Vec<Stmt>) -> Self {
+        Self {
+            stmts,
+            instrs: vec![],
+            spans: vec![],
+            span: Span::default(),
+            result: false,
+            offset: 0,
+            functions: vec![FunctionScope::default()],
+        }
+    }
+
+    // The instructions are added after the offset instructions already run, and leave a result
+    pub(crate) fn eval(stmts: Vec<Stmt>, offset: usize) -> Self {
+        Self {
+            result: true,
+            offset,
+            ..Self::new(stmts)
+        }
+    }
+
+    // The instructions along with the span of each
+    pub(crate) fn compile(mut self) -> Result<(Vec<Instr>, Vec<Span>)> {
+        let stmts = std::mem::take(&mut self.stmts);
+
+        let (body, result) = match stmts.split_last() {
+            Some((
+                Stmt {
+                    kind: StmtKind::Expr(expr),
+                    ..
+                },
+                body,
+            )) if self.result => (body, Some(expr)),
+            _ => (&stmts[..], None),
+        };
+
+        self.compile_stmts(body)?;
+
+        if self.result {
+            match result {
+                Some(expr) => self.compile_expr(expr)?,
+                None => self.emit(Instr::Push(Value::Nil)),
+            }
+        }
+
+        Ok((self.instrs, self.spans))
+    }
+
+    fn compile_stmt(&mut self, stmt: &Stmt) -> Result {
+        let outer = std::mem::replace(&mut self.span, stmt.span);
+
+        match &stmt.kind {
+            StmtKind::Binding(name, expr) => {
+                self.compile_expr(expr)?;
+
+                let store = self.declare(name);
+
+                self.emit(store);
+            }
+            StmtKind::Assign(name, expr) => {
+                self.compile_expr(expr)?;
+
+                let store = self.assign(name);
+
+                self.emit(store);
+            }
+            StmtKind::IndexAssign(target, index, expr) => {
+                self.compile_expr(target)?;
+
+                self.compile_expr(index)?;
+
+                self.compile_expr(expr)?;
+
+                self.emit(Instr::StoreIndex);
+            }
+            StmtKind::Print(expr) => {
+                self.compile_expr(expr)?;
+
+                self.emit(Instr::Print);
+            }
+            StmtKind::If(cond, then_block, else_block) => {
+                self.compile_expr(cond)?;
+
+                let jump_to_else = self.addr();
+
+                // Patched once the start of the else block is known
+                self.emit(Instr::PopJumpFalse(0));
+
+                // Popping the scope jumps to the end of the if, skipping the else block
+                let then_scope = self.compile_block(then_block)?;
+
+                self.patch(jump_to_else, Instr::PopJumpFalse(self.addr()));
+
+                let else_scope = match else_block {
+                    Some(block) => Some(self.compile_block(block)?),
+                    None => None,
+                };
+
+                let end = self.addr();
+
+                self.patch(then_scope, Instr::PushScope(end));
+
+                if let Some(else_scope) = else_scope {
+                    self.patch(else_scope, Instr::PushScope(end));
+                }
+            }
+            StmtKind::While(cond, block) => {
+                /*
+                 * PushScope(end)
+                 * start: <cond>
+                 * PopJumpFalse(pop)
+                 * <block>
+                 * Jump(start)
+                 * pop: PopScope
+                 * end:
+                 */
+                let scope = self.addr();
+
+                self.emit(Instr::PushScope(0));
+
+                self.scopes_mut().push(HashSet::new());
+
+                let start = self.addr();
+
+                self.compile_expr(cond)?;
+
+                let jump_to_pop = self.addr();
+
+                self.emit(Instr::PopJumpFalse(0));
+
+                let compiled = self.compile_stmts(block);
+
+                self.scopes_mut().pop();
+
+                compiled?;
+
+                self.emit(Instr::Jump(start));
+
+                self.patch(jump_to_pop, Instr::PopJumpFalse(self.addr()));
+
+                self.emit(Instr::PopScope);
+
+                self.patch(scope, Instr::PushScope(self.addr()));
+            }
+            StmtKind::Function(name, params, body) => {
+                self.compile_function(Some(name), params, body)?;
+
+                // Local functions were declared at the start of their scope
+                let store = self.assign(name);
+
+                self.emit(store);
+            }
+            StmtKind::Return(expr) => {
+                // The top level isn't a function
+                if self.functions.len() == 1 {
+                    return Err(ErrorKind::ReturnOutsideFunction(stmt.span));
+                }
+
+                match expr {
+                    Some(expr) => self.compile_expr(expr)?,
+                    None => self.emit(Instr::Push(Value::Nil)),
+                }
+
+                self.emit(Instr::Return);
+            }
+            StmtKind::Expr(expr) => {
+                self.compile_expr(expr)?;
+
+                self.emit(Instr::Pop);
+            }
+        }
+
+        self.span = outer;
+
+        Ok(())
+    }
+
+    // The functions declared in a local scope are declared as nil at its start, so closures made
+    // before a function can capture it, and functions can call each other. Each is set once its
+    // declaration is reached. Globals are looked up when used, so they don't need this.
+    fn compile_stmts(&mut self, stmts: &[Stmt]) -> Result {
+        if !self.scopes_mut().is_empty() {
+            for stmt in stmts {
+                if let StmtKind::Function(name, ..) = &stmt.kind {
+                    self.emit(Instr::Push(Value::Nil));
+
+                    let declare = self.declare(name);
+
+                    self.emit(declare);
+                }
+            }
+        }
+
+        stmts.iter().try_for_each(|stmt| self.compile_stmt(stmt))
+    }
+
+    fn compile_expr(&mut self, expr: &Expr) -> Result {
+        let outer = std::mem::replace(&mut self.span, expr.span);
+
+        match &expr.kind {
+            ExprKind::Number(num) => self.emit(Instr::Push(Value::Int(*num))),
+            ExprKind::BigNumber(num) => self.emit(Instr::Push(Value::BigInt(num.clone()))),
+            ExprKind::Float(num) => self.emit(Instr::Push(Value::Float(*num))),
+            ExprKind::Bool(val) => self.emit(Instr::Push(Value::Bool(*val))),
+            ExprKind::Nil => self.emit(Instr::Push(Value::Nil)),
+            ExprKind::String(string) => self.emit(Instr::Push(Value::String(string.to_string()))),
+            ExprKind::Ident(name) => {
+                self.resolve(self.functions.len() - 1, name);
+
+                self.emit(Instr::Load(name.to_string()));
+            }
+            ExprKind::Binop(BinopKind::And, left, right) => {
+                self.compile_logical(false, left, right)?
+            }
+            ExprKind::Binop(BinopKind::Or, left, right) => {
+                self.compile_logical(true, left, right)?
+            }
+            ExprKind::Binop(kind, left, right) => {
+                // Operands are pushed left to right, the VM pops the right operand first
+                self.compile_expr(left)?;
+
+                self.compile_expr(right)?;
+
+                let instr = match kind {
+                    BinopKind::Plus => Instr::Binop(InstrBinopKind::Plus),
+                    BinopKind::Minus => Instr::Binop(InstrBinopKind::Minus),
+                    BinopKind::Times => Instr::Binop(InstrBinopKind::Times),
+                    BinopKind::Divide => Instr::Binop(InstrBinopKind::Divide),
+                    BinopKind::Modulo => Instr::Binop(InstrBinopKind::Modulo),
+                    BinopKind::And | BinopKind::Or => unreachable!("compiled by compile_logical"),
+                    BinopKind::Equal => Instr::Compare(CompareKind::Equal),
+                    BinopKind::NotEqual => Instr::Compare(CompareKind::NotEqual),
+                    BinopKind::LessThan => Instr::Compare(CompareKind::LessThan),
+                    BinopKind::LessThanOrEqual => Instr::Compare(CompareKind::LessThanOrEqual),
+                    BinopKind::GreaterThan => Instr::Compare(CompareKind::GreaterThan),
+                    BinopKind::GreaterThanOrEqual => {
+                        Instr::Compare(CompareKind::GreaterThanOrEqual)
+                    }
+                };
+
+                self.emit(instr);
+            }
+            ExprKind::Unary(kind, expr) => {
+                self.compile_expr(expr)?;
+
+                let kind = match kind {
+                    UnaryKind::Negate => InstrUnaryKind::Negate,
+                    UnaryKind::Not => InstrUnaryKind::Not,
+                };
+
+                self.emit(Instr::Unary(kind));
+            }
+            ExprKind::Call(callee, args) => {
+                self.compile_call(callee, args)?;
+
+                self.emit(Instr::Call(args.len()));
+            }
+            ExprKind::Function(params, body) => self.compile_function(None, params, body)?,
+            ExprKind::List(items) => {
+                for item in items {
+                    self.compile_expr(item)?;
+                }
+
+                self.emit(Instr::List(items.len()));
+            }
+            ExprKind::Map(entries) => {
+                for (key, val) in entries {
+                    self.compile_expr(key)?;
+
+                    self.compile_expr(val)?;
+                }
+
+                self.emit(Instr::Map(entries.len()));
+            }
+            ExprKind::Index(target, index) => {
+                self.compile_expr(target)?;
+
+                self.compile_expr(index)?;
+
+                self.emit(Instr::Index);
+            }
+            ExprKind::Slice(target, start, end) => {
+                self.compile_expr(target)?;
+
+                // The bounds which were left out aren't pushed
+                for bound in start.iter().chain(end) {
+                    self.compile_expr(bound)?;
+                }
+
+                self.emit(Instr::Slice(start.is_some(), end.is_some()));
+            }
+        }
+
+        self.span = outer;
+
+        Ok(())
+    }
+
+    /*
+     * && and || only run the right operand if the left one doesn't decide the result, so guards
+     * like `x != nil && x > 0` work. Short is the value which decides it, false for &&:
+     *
+     * <left>
+     * PopJumpFalse(short)
+     * <right>
+     * PopJumpFalse(short)
+     * Push(true)
+     * Jump(end)
+     * short: Push(false)
+     * end:
+     *
+     * || is the same with PopJumpTrue and the pushed values swapped.
+     */
+    fn compile_logical(&mut self, short: bool, left: &Expr, right: &Expr) -> Result {
+        let jump = |addr| {
+            if short {
+                Instr::PopJumpTrue(addr)
+            } else {
+                Instr::PopJumpFalse(addr)
+            }
+        };
+
+        self.compile_expr(left)?;
+
+        let jump_from_left = self.addr();
+
+        self.emit(jump(0));
+
+        self.compile_expr(right)?;
+
+        let jump_from_right = self.addr();
+
+        self.emit(jump(0));
+
+        self.emit(Instr::Push(Value::Bool(!short)));
+
+        let jump_to_end = self.addr();
+
+        self.emit(Instr::Jump(0));
+
+        self.patch(jump_from_left, jump(self.addr()));
+
+        self.patch(jump_from_right, jump(self.addr()));
+
+        self.emit(Instr::Push(Value::Bool(short)));
+
+        self.patch(jump_to_end, Instr::Jump(self.addr()));
+
+        Ok(())
+    }
+
+    // Pushes the function followed by its arguments, leaving the call itself to the caller
+    fn compile_call(&mut self, callee: &Expr, args: &[Expr]) -> Result {
+        self.compile_expr(callee)?;
+
+        args.iter().try_for_each(|arg| self.compile_expr(arg))
+    }
+
+    // Compiles the body out of line, leaving a closure over it on the stack
+    fn compile_function(&mut self, name: Option<&str>, params: &[String], body: &[Stmt]) -> Result {
+        let jump_over = self.addr();
+
+        // The body is only run when called, patched once its end is known
+        self.emit(Instr::Jump(0));
+
+        let entry = self.addr();
+
+        let mut scope = params.iter().cloned().collect::<HashSet<_>>();
+
+        scope.extend(name.map(str::to_string));
+
+        self.functions.push(FunctionScope {
+            scopes: vec![scope],
+            captures: vec![],
+        });
+
+        let compiled = self.compile_stmts(body);
+
+        let function = self.functions.pop().unwrap_or_default();
+
+        compiled?;
+
+        // Falling off the end of the body returns nil
+        self.emit(Instr::Push(Value::Nil));
+
+        self.emit(Instr::Return);
+
+        self.patch(jump_over, Instr::Jump(self.addr()));
+
+        let function = Function::new(name, params, entry, &function.captures);
+
+        self.emit(Instr::Closure(Rc::new(function)));
+
+        Ok(())
+    }
+
+    fn scopes_mut(&mut self) -> &mut Vec<HashSet<String>> {
+        let depth = self.functions.len() - 1;
+
+        &mut self.functions[depth].scopes
+    }
+
+    // Bindings at the top level are globals, everywhere else they're local to the scope
+    fn declare(&mut self, name: &str) -> Instr {
+        match self.scopes_mut().last_mut() {
+            Some(scope) => {
+                scope.insert(name.to_string());
+
+                Instr::Declare(name.to_string())
+            }
+            None => Instr::StoreGlobal(name.to_string()),
+        }
+    }
+
+    // Assigning to a name that isn't a local sets a global, creating it if needs be
+    fn assign(&mut self, name: &str) -> Instr {
+        if self.resolve(self.functions.len() - 1, name) {
+            Instr::Store(name.to_string())
+        } else {
+            Instr::StoreGlobal(name.to_string())
+        }
+    }
+
+    // Whether the name is a local of the function at the depth. Locals of the enclosing functions
+    // are captured by every function in between, so that each closure can hand them on.
+    fn resolve(&mut self, depth: usize, name: &str) -> bool {
+        let function = &self.functions[depth];
+
+        if function.scopes.iter().any(|scope| scope.contains(name))
+            || function.captures.iter().any(|capture| capture == name)
+        {
+            return true;
+        }
+
+        let captured = depth > 0 && self.resolve(depth - 1, name);
+
+        if captured {
+            self.functions[depth].captures.push(name.to_string());
+        }
+
+        captured
+    }
+
+    // Compiles the statements in their own scope, returning the address of the PushScope to be
+    // patched with the address to continue at after the scope is popped
+    fn compile_block(&mut self, stmts: &[Stmt]) -> Result<usize> {
+        let scope = self.addr();
+
+        self.emit(Instr::PushScope(0));
+
+        self.scopes_mut().push(HashSet::new());
+
+        let compiled = self.compile_stmts(stmts);
+
+        self.scopes_mut().pop();
+
+        compiled?;
+
+        self.emit(Instr::PopScope);
+
+        Ok(scope)
+    }
+
+    fn emit(&mut self, instr: Instr) {
+        self.instrs.push(instr);
+
+        self.spans.push(self.span);
+    }
+
+    // The address of the next instruction to be emitted
+    fn addr(&self) -> usize {
+        self.offset + self.instrs.len()
+    }
+
+    fn patch(&mut self, addr: usize, instr: Instr) {
+        self.instrs[addr - self.offset] = instr;
+    }
+}
+
+impl ErrorKind {
+    // The whole `return` statement, including its value
+    pub fn span(&self) -> Span {
+        match self {
+            ErrorKind::ReturnOutsideFunction(span) => *span,
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::ReturnOutsideFunction(_) => write!(f, "return outside of a function"),
+        }
+    }
+}
+
+impl std::error::Error for ErrorKind {}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        lexer::Lexer,
+        parser::Parser,
+        vm::{
+            inter::Inter,
+            stack::{StackErrorKind, StackKind},
+            value::Value,
+            ErrorKind as VmErrorKind,
+        },
+    };
+
+    use super::{Compiler, ErrorKind};
+
+    fn compile(input: &str) -> Result<Inter, ErrorKind> {
+        let tokens = Lexer::new(input).run().expect("lexing failed");
+
+        let stmts = Parser::new(tokens).parse().expect("parsing failed");
+
+        let mut inter = Inter::new().expect("creating interpreter failed");
+
+        let (instrs, spans) = Compiler::new(stmts).compile()?;
+
+        inter.push_instrs(&instrs, &spans);
+
+        Ok(inter)
+    }
+
+    fn try_run(input: &str) -> Result<Inter, VmErrorKind> {
+        let mut inter = compile(input).expect("compiling failed");
+
+        inter.run()?;
+
+        Ok(inter)
+    }
+
+    fn run(input: &str) -> Inter {
+        try_run(input).expect("running failed")
+    }
+
+    fn global(inter: &Inter, name: &str) -> Option<Value> {
+        inter.evaler.globals.get(name).cloned()
+    }
+
+    fn local(inter: &Inter, name: &str) -> Option<Value> {
+        inter
+            .evaler
+            .frames
+            .top()
+            .ok()
+            .and_then(|frame| frame.get_local(name))
+    }
+
+    #[test]
+    fn compiling_binding_works() {
+        let inter = run("let x = 400");
+
+        assert_eq!(global(&inter, "x"), Some(Value::Int(400)));
+    }
+
+    #[test]
+    fn compiling_binop_works() {
+        let inter = run("let x = 100 + 100 * 200");
+
+        assert_eq!(global(&inter, "x"), Some(Value::Int(20100)));
+    }
+
+    #[test]
+    fn compiling_expr_stmt_leaves_stack_empty() {
+        let inter = run("1 + 2");
+
+        assert!(inter.evaler.frames.top().unwrap().vals.is_empty());
+    }
+
+    #[test]
+    fn compiling_operator_chains_works() {
+        let inter = run("let x = 100 - 10 - 20 * 3 % 7 / 2");
+
+        assert_eq!(global(&inter, "x"), Some(Value::Int(88)));
+    }
+
+    #[test]
+    fn compiling_comparisons_works() {
+        let inter = run("let x = 1 + 1 == 2 && !(3 < 2) || -1 > 0");
+
+        assert_eq!(global(&inter, "x"), Some(Value::Bool(true)));
+    }
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        let inter = run(r#"
+            let x = nil
+            let positive = x != nil && x > 0
+            let xs = [0, 1]
+            let i = 2
+            let zero = i < len(xs) && xs[i] == 0
+            let missing = x == nil || x < 0
+        "#);
+
+        assert_eq!(global(&inter, "positive"), Some(Value::Bool(false)));
+
+        assert_eq!(global(&inter, "zero"), Some(Value::Bool(false)));
+
+        assert_eq!(global(&inter, "missing"), Some(Value::Bool(true)));
+    }
+
+    #[test]
+    fn logical_operators_work() {
+        for (input, result) in &[
+            ("true && true", true),
+            ("true && false", false),
+            ("false && true", false),
+            ("false || false", false),
+            ("false || true", true),
+            ("true || false", true),
+        ] {
+            let inter = run(&format!("let x = {}", input));
+
+            assert_eq!(global(&inter, "x"), Some(Value::Bool(*result)), "{}", input);
+        }
+
+        assert!(matches!(
+            try_run("let x = true && 1"),
+            Err(VmErrorKind::InvalidJumpValue(Value::Int(1)))
+        ));
+    }
+
+    #[test]
+    fn compiling_idents_works() {
+        let inter = run("let x = 400\nlet y = x * 2\nlet z = y > x");
+
+        assert_eq!(global(&inter, "y"), Some(Value::Int(800)));
+
+        assert_eq!(global(&inter, "z"), Some(Value::Bool(true)));
+    }
+
+    #[test]
+    fn compiling_literals_works() {
+        let inter = run(r#"let x = "hello"; let y = !false"#);
+
+        assert_eq!(
+            global(&inter, "x"),
+            Some(Value::String("hello".to_string()))
+        );
+
+        assert_eq!(global(&inter, "y"), Some(Value::Bool(true)));
+    }
+
+    #[test]
+    fn compiling_if_works() {
+        let inter = run("let x = 1\nif x == 1 { x = 2 } else { x = 3 }\nif x == 1 { x = 4 }");
+
+        assert_eq!(global(&inter, "x"), Some(Value::Int(2)));
+
+        let else_inter =
+            run("let x = 5\nif x == 1 { x = 2 } else if x == 5 { x = 3 } else { x = 4 }");
+
+        assert_eq!(global(&else_inter, "x"), Some(Value::Int(3)));
+    }
+
+    #[test]
+    fn compiling_while_works() {
+        let inter =
+            run("let i = 0\nlet total = 0\nwhile i < 5 {\n  i = i + 1\n  total = total + i\n}");
+
+        assert_eq!(global(&inter, "i"), Some(Value::Int(5)));
+
+        assert_eq!(global(&inter, "total"), Some(Value::Int(15)));
+    }
+
+    #[test]
+    fn compiling_blocks_scopes_locals() {
+        let inter = run("let i = 0\nwhile i < 2 { let y = i; i = i + 1 }\nif true { let z = 1 }");
+
+        assert_eq!(global(&inter, "y"), None);
+
+        assert_eq!(local(&inter, "y"), None);
+
+        assert_eq!(local(&inter, "z"), None);
+
+        assert!(inter.evaler.frames.top().unwrap().vals.is_empty());
+
+        assert_eq!(inter.evaler.frames.top().unwrap().blocks.len(), 1);
+    }
+
+    #[test]
+    fn compiling_functions_works() {
+        let inter = run("fn add(x, y) {\n  return x + y\n}\nlet z = add(1, 2) * add(3, 4)");
+
+        assert_eq!(global(&inter, "z"), Some(Value::Int(21)));
+
+        assert!(inter.evaler.frames.top().unwrap().vals.is_empty());
+
+        assert_eq!(inter.evaler.frames.len(), 1);
+    }
+
+    #[test]
+    fn compiling_recursion_works() {
+        let inter = run("fn fib(n) {\n  if n < 2 { return n }\n  return fib(n - 1) + fib(n - 2)\n}\nlet x = fib(15)");
+
+        assert_eq!(global(&inter, "x"), Some(Value::Int(610)));
+    }
+
+    #[test]
+    fn compiling_function_locals_works() {
+        let inter = run("let x = 1\nfn f(x) {\n  let y = x\n  fn g() { return 3 }\n  counter = counter + g()\n  return y\n}\nlet counter = 0\nlet z = f(5)\nf(6)");
+
+        assert_eq!(global(&inter, "x"), Some(Value::Int(1)));
+
+        assert_eq!(global(&inter, "z"), Some(Value::Int(5)));
+
+        assert_eq!(global(&inter, "counter"), Some(Value::Int(6)));
+
+        assert_eq!(global(&inter, "y"), None);
+
+        assert_eq!(global(&inter, "g"), None);
+    }
+
+    #[test]
+    fn compiling_nil_works() {
+        let inter = run("fn f() {}\nfn g(x) {\n  if x { return }\n  return 1\n}\nlet a = f()\nlet b = g(true)\nlet c = g(false)\nlet d = get({\"k\": 1}, \"j\")\nlet e = nil == nil\nlet h = [nil]");
+
+        assert_eq!(global(&inter, "a"), Some(Value::Nil));
+
+        assert_eq!(global(&inter, "b"), Some(Value::Nil));
+
+        assert_eq!(global(&inter, "c"), Some(Value::Int(1)));
+
+        assert_eq!(global(&inter, "d"), Some(Value::Nil));
+
+        assert_eq!(global(&inter, "e"), Some(Value::Bool(true)));
+
+        assert_eq!(global(&inter, "h").unwrap().to_string(), "[nil]");
+    }
+
+    #[test]
+    fn calling_functions_fails_on_bad_calls() {
+        assert!(matches!(
+            try_run("fn f(x) { return x }\nf(1, 2)"),
+            Err(VmErrorKind::ArityMismatch {
+                expected: 1,
+                found: 2,
+                ..
+            })
+        ));
+
+        assert!(matches!(
+            try_run("let f = 1\nf()"),
+            Err(VmErrorKind::InvalidCall(Value::Int(1)))
+        ));
+    }
+
+    #[test]
+    fn runaway_recursion_overflows() {
+        assert!(matches!(
+            try_run("fn f(n) { return f(n + 1) }\nf(0)"),
+            Err(VmErrorKind::StackError(
+                StackKind::Frame,
+                StackErrorKind::StackOverflow
+            ))
+        ));
+    }
+
+    #[test]
+    fn compiling_return_outside_function_fails() {
+        assert!(matches!(
+            compile("while true { return 1 }"),
+            Err(ErrorKind::ReturnOutsideFunction(_))
+        ));
+    }
+
+    #[test]
+    fn compiling_closures_works() {
+        let inter = run("fn counter() {\n  let count = 0\n  return fn() {\n    count = count + 1\n    return count\n  }\n}\nlet a = counter()\nlet b = counter()\na()\na()\nb()\nlet x = a()\nlet y = b()");
+
+        assert_eq!(global(&inter, "x"), Some(Value::Int(3)));
+
+        assert_eq!(global(&inter, "y"), Some(Value::Int(2)));
+    }
+
+    #[test]
+    fn compiling_nested_closures_works() {
+        let inter = run("fn outer(x) {\n  fn middle() {\n    return fn(y) { return x + y }\n  }\n  x = x * 10\n  return middle()\n}\nlet add = outer(2)\nlet x = add(1)");
+
+        assert_eq!(global(&inter, "x"), Some(Value::Int(21)));
+    }
+
+    #[test]
+    fn compiling_closures_as_callbacks_works() {
+        let inter = run("fn apply(f, x) { return f(x) }\nlet total = 0\nlet i = 0\nwhile i < 3 {\n  let step = i\n  total = apply(fn(x) {\n    return x + step\n  }, total)\n  i = i + 1\n}");
+
+        assert_eq!(global(&inter, "total"), Some(Value::Int(3)));
+    }
+
+    #[test]
+    fn local_functions_can_be_used_before_their_declaration() {
+        let inter = run(r#"
+            fn f() {
+                let g = fn() { return h() }
+                fn h() { return 1 }
+                return g()
+            }
+            let x = f()
+        "#);
+
+        assert_eq!(global(&inter, "x"), Some(Value::Int(1)));
+    }
+
+    #[test]
+    fn local_functions_can_call_each_other() {
+        let inter = run(r#"
+            fn parity(n) {
+                fn is_even(n) {
+                    if n == 0 { return true }
+                    return is_odd(n - 1)
+                }
+                fn is_odd(n) {
+                    if n == 0 { return false }
+                    return is_even(n - 1)
+                }
+                return is_even(n)
+            }
+            let x = parity(10)
+            let y = parity(7)
+        "#);
+
+        assert_eq!(global(&inter, "x"), Some(Value::Bool(true)));
+
+        assert_eq!(global(&inter, "y"), Some(Value::Bool(false)));
+    }
+
+    #[test]
+    fn compiling_shadowed_locals_works() {
+        let inter = run("fn f() {\n  let x = 1\n  if true {\n    let x = 2\n    x = 3\n  }\n  return x\n}\nlet x = f()");
+
+        assert_eq!(global(&inter, "x"), Some(Value::Int(1)));
+    }
+
+    #[test]
+    fn compiling_floats_works() {
+        let inter = run("let x = 1.5 * 2\nlet y = 7 / 2\nlet z = 1 < 1.5\nlet w = -2e-1");
+
+        assert_eq!(global(&inter, "x"), Some(Value::Float(3.0)));
+
+        assert_eq!(global(&inter, "y"), Some(Value::Int(3)));
+
+        assert_eq!(global(&inter, "z"), Some(Value::Bool(true)));
+
+        assert_eq!(global(&inter, "w"), Some(Value::Float(-0.2)));
+    }
+
+    #[test]
+    fn compiling_big_ints_works() {
+        let inter = run("fn fact(n) {\n  if n < 2 { return 1 }\n  return n * fact(n - 1)\n}\nlet x = fact(25)\nlet y = x / fact(24)\nlet z = 99999999999999999999 > 1\nlet w = x + 0.5");
+
+        assert_eq!(
+            global(&inter, "x"),
+            Some(Value::BigInt("15511210043330985984000000".parse().unwrap()))
+        );
+
+        assert_eq!(global(&inter, "y"), Some(Value::Int(25)));
+
+        assert_eq!(global(&inter, "z"), Some(Value::Bool(true)));
+
+        assert_eq!(
+            global(&inter, "w"),
+            Some(Value::Float(1.5511210043330986e25))
+        );
+    }
+
+    #[test]
+    fn compiling_binop_keeps_operand_order() {
+        let minus_inter = run("let x = 10 - 4");
+
+        let divide_inter = run("let x = 8 / (6 - 2)");
+
+        assert_eq!(global(&minus_inter, "x"), Some(Value::Int(6)));
+
+        assert_eq!(global(&divide_inter, "x"), Some(Value::Int(2)));
+    }
+}
@@ -0,0 +1,266 @@
+use crate::{
+    compiler::Compiler,
+    lexer::Lexer,
+    parser::Parser,
+    vm::{inter::Inter, native::NativeFn, stack::Limits, trace::Traceback, value::Value},
+    ErrorKind, Result,
+};
+
+// The interpreter as seen by programs embedding it. Each call to eval runs after the ones before
+// it, so globals and functions defined by earlier sources can be used by later ones.
+#[derive(Debug)]
+pub struct Engine {
+    inter: Inter,
+}
+
+impl Engine {
+    pub fn new() -> Result<Self> {
+        Self::with_limits(Limits::default())
+    }
+
+    pub fn with_limits(limits: Limits) -> Result<Self> {
+        Ok(Self {
+            inter: Inter::with_limits(limits)
+                .map_err(|err| ErrorKind::VmError(err, Traceback::default()))?,
+        })
+    }
+
+    // Runs the source, returning the value of its last statement if that's an expression, or
+    // nil otherwise. If running fails, whatever was running is abandoned but globals are kept.
+    pub fn eval(&mut self, source: &str) -> Result<Value> {
+        let tokens = Lexer::new(source).run().map_err(ErrorKind::LexerError)?;
+
+        let stmts = Parser::new(tokens)
+            .parse()
+            .map_err(ErrorKind::ParserError)?;
+
+        let offset = self.inter.instrs.len();
+
+        let (instrs, spans) = Compiler::eval(stmts, offset)
+            .compile()
+            .map_err(ErrorKind::CompilerError)?;
+
+        self.inter.push_instrs(&instrs, &spans);
+
+        if let Err((err, traceback)) = self.inter.run_traced() {
+            self.inter
+                .unwind()
+                .map_err(|err| ErrorKind::VmError(err, Traceback::default()))?;
+
+            return Err(ErrorKind::VmError(err, traceback));
+        }
+
+        self.inter
+            .evaler
+            .frames
+            .top_mut()
+            .and_then(|frame| frame.vals.pop())
+            .map_err(|err| ErrorKind::VmError(err, Traceback::default()))
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.inter.global(name).cloned()
+    }
+
+    pub fn set(&mut self, name: &str, val: Value) {
+        self.inter.set_global(name, val);
+    }
+
+    // Makes the function callable by scripts as a global of the given name
+    pub fn register(&mut self, name: &str, arity: usize, func: NativeFn) {
+        self.inter.register(name, arity, func);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::error::Error;
+
+    use crate::{
+        vm::{stack::Limits, value::Value, ErrorKind as VmErrorKind},
+        ErrorKind, Result,
+    };
+
+    use super::Engine;
+
+    #[test]
+    fn eval_returns_the_last_expression() -> Result {
+        let mut engine = Engine::new()?;
+
+        assert_eq!(engine.eval("let x = 2\nx * 21")?, Value::Int(42));
+
+        assert_eq!(engine.eval("let y = 1")?, Value::Nil);
+
+        assert_eq!(engine.eval("")?, Value::Nil);
+
+        Ok(())
+    }
+
+    #[test]
+    fn globals_persist_between_evals() -> Result {
+        let mut engine = Engine::new()?;
+
+        engine.eval("fn double(x) { return x * 2 }")?;
+
+        engine.set("x", Value::Int(4));
+
+        assert_eq!(engine.eval("double(x)")?, Value::Int(8));
+
+        engine.eval("let y = [double(1)]")?;
+
+        assert_eq!(engine.get("y"), Some(Value::list(vec![Value::Int(2)])));
+
+        assert_eq!(engine.get("z"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn registered_functions_are_callable() -> Result {
+        let mut engine = Engine::new()?;
+
+        engine.register("answer", 0, |_, _| Ok(Value::Int(42)));
+
+        assert_eq!(engine.eval("answer() + 1")?, Value::Int(43));
+
+        Ok(())
+    }
+
+    #[test]
+    fn engine_is_usable_after_errors() -> Result {
+        let mut engine = Engine::new()?;
+
+        engine.eval("let x = 1")?;
+
+        assert!(matches!(
+            engine.eval("let y = ("),
+            Err(ErrorKind::ParserError(_))
+        ));
+
+        assert!(matches!(
+            engine.eval("fn f() { return x + nil }\nf()"),
+            Err(ErrorKind::VmError(VmErrorKind::InvalidBinop { .. }, _))
+        ));
+
+        assert_eq!(engine.eval("x")?, Value::Int(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn errors_have_readable_messages() -> Result {
+        let mut engine = Engine::new()?;
+
+        engine.eval("fn f(a) { return f(a) }")?;
+
+        for (source, message) in &[
+            ("1 + true", "cannot apply `+` to int 1 and bool true"),
+            ("-nil", "cannot apply `-` to nil"),
+            ("x", "unknown name `x`"),
+            ("f()", "`f` takes 1 argument but was given 0"),
+            ("f(1)", "call stack overflow"),
+            ("5 < \"a\"", "cannot order int 5 and string \"a\""),
+            ("[1][3]", "index 3 is out of bounds for length 1"),
+            ("{\"a\": 1}[\"b\"]", "key string \"b\" not found"),
+            ("1 % 0", "`%` by zero"),
+            ("len(f)", "invalid argument to `len`: <fn f>"),
+            ("if [] {}", "expected a bool condition, found list []"),
+        ] {
+            let err = engine.eval(source).unwrap_err();
+
+            assert_eq!(err.to_string(), format!("runtime error: {}", message));
+
+            assert_eq!(
+                err.source().map(ToString::to_string).as_deref(),
+                Some(*message)
+            );
+
+            assert!(err.render(source).starts_with(&format!(
+                "error: {}\ntraceback (most recent call last):\n  at <script>, line 1, col 1",
+                message
+            )));
+        }
+
+        let err = engine.eval("let x = (").unwrap_err();
+
+        assert_eq!(err.to_string(), "syntax error: unexpected end of input");
+
+        assert_eq!(
+            err.source().map(ToString::to_string).as_deref(),
+            Some("unexpected end of input")
+        );
+
+        let err = engine.eval("let = 1\nlet x = (").unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "2 syntax errors, the first: unexpected token `=`"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn runtime_errors_have_a_traceback() -> Result {
+        let mut engine = Engine::new()?;
+
+        engine.eval("fn inner(x) {\n  return x + nil\n}")?;
+
+        let source = "fn outer() {\n  let y = 1\n  return inner(y)\n}\nlet z = 2 * outer()";
+
+        let err = engine.eval(source).unwrap_err();
+
+        let traceback = match &err {
+            ErrorKind::VmError(VmErrorKind::InvalidBinop { .. }, traceback) => traceback,
+            err => panic!("unexpected error {:?}", err),
+        };
+
+        let frames = traceback
+            .frames()
+            .iter()
+            .map(|frame| (frame.function(), frame.position()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            frames,
+            vec![
+                (None, Some((5, 13))),
+                (Some("outer"), Some((3, 10))),
+                (Some("inner"), Some((2, 10))),
+            ]
+        );
+
+        assert_eq!(
+            traceback.pc(),
+            traceback.frames().last().map(|frame| frame.pc())
+        );
+
+        assert_eq!(
+            err.render(source),
+            "error: cannot apply `+` to int 1 and nil\n\
+             traceback (most recent call last):\n  \
+             at <script>, line 5, col 13\n  \
+             at outer, line 3, col 10\n  \
+             at inner, line 2, col 10"
+        );
+
+        // Recursing until the stack overflows doesn't list every call
+        let err = engine
+            .eval("fn f(n) {\n  return f(n + 1)\n}\nf(0)")
+            .unwrap_err();
+
+        assert!(err.render("").ends_with(&format!(
+            "at f, line 2, col 10\n  ... repeated {} more times",
+            Limits::default().frames - 2
+        )));
+
+        // Natives which fail are reported at the call
+        let err = engine
+            .eval("fn f() {\n  return pop([])\n}\nf()")
+            .unwrap_err();
+
+        assert!(err.render("").ends_with("at f, line 2, col 10"));
+
+        Ok(())
+    }
+}
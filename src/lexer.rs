@@ -1,118 +1,374 @@
-use std::iter::Peekable;
+use std::{fmt, iter::Peekable};
+
+use crate::{span::Span, vm::bigint::BigInt};
 
 type Result<T = ()> = std::result::Result<T, ErrorKind>;
 
-const SINGLE_CHAR_TOKENS: [char; 7] = ['(', ')', '+', '-', '*', '/', '='];
+const SINGLE_CHAR_TOKENS: [char; 14] = [
+    '(', ')', '{', '}', '[', ']', ',', ':', '+', '-', '*', '/', '%', ';',
+];
+
+// Chars that start an operator which might be one or two chars long, like < and <=
+const OPERATOR_TOKENS: [char; 6] = ['=', '!', '<', '>', '&', '|'];
 
 #[derive(Clone, Debug, PartialEq)]
-pub(crate) enum Token {
+pub(crate) enum TokenKind {
     Number(i32),
+    BigNumber(BigInt),
+    Float(f64),
+    String(String),
     Ident(String),
     Let,
+    Print,
+    True,
+    False,
+    Nil,
+    If,
+    Else,
+    While,
+    Fn,
+    Return,
     LBracket,
     RBracket,
+    LBrace,
+    RBrace,
+    LSquare,
+    RSquare,
+    Comma,
+    Colon,
     Plus,
     Minus,
     Times,
     Divide,
+    Modulo,
     Equal,
+    DoubleEqual,
+    NotEqual,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+    And,
+    Or,
+    Not,
+    Semicolon,
+    Newline,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Token {
+    pub(crate) kind: TokenKind,
+    pub(crate) span: Span,
+
+    // The column just after the token, in chars like the span's column
+    pub(crate) end_col: usize,
 }
 
 #[derive(Copy, Clone, Debug)]
-pub(crate) enum ErrorKind {
-    UnexpectedToken(char),
+#[non_exhaustive]
+pub enum ErrorKind {
+    UnexpectedToken(char, Span),
+    UnterminatedString(Span),
+    UnknownEscape(char, Span),
 }
 
 pub(crate) struct Lexer {
     input: String,
+
+    // The current line, and the byte offset it starts at
+    line: usize,
+    line_start: usize,
 }
 
 impl Lexer {
     pub(crate) fn new(input: &str) -> Self {
         Lexer {
             input: input.to_string(),
+            line: 1,
+            line_start: 0,
         }
     }
 
     pub(crate) fn run(&mut self) -> Result<Vec<Token>> {
-        let mut tokens = self.input.chars().peekable();
+        let mut tokens = self.input.char_indices().peekable();
 
         let mut result = vec![];
 
-        while let Some(&lexeme) = tokens.peek() {
+        // The brackets and braces which are open. Newlines directly inside brackets, round or
+        // square, are only whitespace, but a function body inside them still separates statements
+        // by newlines
+        let mut open = vec![];
+
+        while let Some(&(start, lexeme)) = tokens.peek() {
             // Easy handling of single char tokens
             if SINGLE_CHAR_TOKENS.contains(&lexeme) {
-                let token = match lexeme {
-                    '(' => Ok(Token::LBracket),
-                    ')' => Ok(Token::RBracket),
-                    '+' => Ok(Token::Plus),
-                    '-' => Ok(Token::Minus),
-                    '*' => Ok(Token::Times),
-                    '/' => Ok(Token::Divide),
-                    '=' => Ok(Token::Equal),
-                    _ => Err(ErrorKind::UnexpectedToken(lexeme)),
+                let end = start + lexeme.len_utf8();
+
+                let span = self.span(start, end);
+
+                let kind = match lexeme {
+                    '(' => Ok(TokenKind::LBracket),
+                    ')' => Ok(TokenKind::RBracket),
+                    '{' => Ok(TokenKind::LBrace),
+                    '}' => Ok(TokenKind::RBrace),
+                    '[' => Ok(TokenKind::LSquare),
+                    ']' => Ok(TokenKind::RSquare),
+                    ',' => Ok(TokenKind::Comma),
+                    ':' => Ok(TokenKind::Colon),
+                    '+' => Ok(TokenKind::Plus),
+                    '-' => Ok(TokenKind::Minus),
+                    '*' => Ok(TokenKind::Times),
+                    '/' => Ok(TokenKind::Divide),
+                    '%' => Ok(TokenKind::Modulo),
+                    ';' => Ok(TokenKind::Semicolon),
+                    _ => Err(ErrorKind::UnexpectedToken(lexeme, span)),
                 };
 
-                result.push(token?);
+                let kind = kind?;
+
+                match kind {
+                    TokenKind::LBracket | TokenKind::LBrace | TokenKind::LSquare => {
+                        open.push(kind.clone())
+                    }
+                    TokenKind::RBracket | TokenKind::RBrace | TokenKind::RSquare => {
+                        open.pop();
+                    }
+                    _ => {}
+                }
+
+                result.push(self.token(kind, start, end));
 
                 tokens.next();
 
                 continue;
             }
 
+            if OPERATOR_TOKENS.contains(&lexeme) {
+                tokens.next();
+
+                let kind = self.lex_operator(lexeme, start, &mut tokens)?;
+
+                result.push(self.token(kind, start, self.offset(&mut tokens)));
+
+                continue;
+            }
+
             match lexeme {
                 num @ '0'..='9' => {
                     tokens.next();
 
-                    result.push(self.lex_number(num, &mut tokens)?);
+                    let kind = self.lex_number(num, start, &mut tokens)?;
+
+                    result.push(self.token(kind, start, self.offset(&mut tokens)));
                 }
                 ident @ 'a'..='z' | ident @ 'A'..='Z' => {
                     tokens.next();
 
                     let ident = self.lex_ident(ident, &mut tokens)?;
 
-                    let token = match ident.as_str() {
-                        "let" => Token::Let,
-                        _ => Token::Ident(ident),
+                    let kind = match ident.as_str() {
+                        "let" => TokenKind::Let,
+                        "print" => TokenKind::Print,
+                        "true" => TokenKind::True,
+                        "false" => TokenKind::False,
+                        "nil" => TokenKind::Nil,
+                        "if" => TokenKind::If,
+                        "else" => TokenKind::Else,
+                        "while" => TokenKind::While,
+                        "fn" => TokenKind::Fn,
+                        "return" => TokenKind::Return,
+                        _ => TokenKind::Ident(ident),
                     };
 
-                    result.push(token);
+                    result.push(self.token(kind, start, self.offset(&mut tokens)));
+                }
+                '"' => {
+                    tokens.next();
+
+                    let kind = self.lex_string(start, &mut tokens)?;
+
+                    result.push(self.token(kind, start, self.offset(&mut tokens)));
+                }
+                '\n' => {
+                    tokens.next();
+
+                    if !matches!(open.last(), Some(TokenKind::LBracket | TokenKind::LSquare)) {
+                        result.push(self.token(TokenKind::Newline, start, start + 1));
+                    }
+
+                    self.line += 1;
+
+                    self.line_start = start + 1;
                 }
                 ' ' | '\t' | '\r' => {
                     tokens.next();
 
                     continue;
                 }
-                _ => return Err(ErrorKind::UnexpectedToken(lexeme)),
+                _ => {
+                    return Err(ErrorKind::UnexpectedToken(
+                        lexeme,
+                        self.span(start, start + lexeme.len_utf8()),
+                    ))
+                }
             }
         }
 
         Ok(result)
     }
 
-    fn lex_number<T: Iterator<Item = char>>(
+    fn lex_operator<T: Iterator<Item = (usize, char)>>(
+        &self,
+        lexeme: char,
+        start: usize,
+        tokens: &mut Peekable<T>,
+    ) -> Result<TokenKind> {
+        let next = tokens.peek().map(|(_, next)| *next);
+
+        let (kind, is_double) = match (lexeme, next) {
+            ('=', Some('=')) => (TokenKind::DoubleEqual, true),
+            ('!', Some('=')) => (TokenKind::NotEqual, true),
+            ('<', Some('=')) => (TokenKind::LessThanOrEqual, true),
+            ('>', Some('=')) => (TokenKind::GreaterThanOrEqual, true),
+            ('&', Some('&')) => (TokenKind::And, true),
+            ('|', Some('|')) => (TokenKind::Or, true),
+            ('=', _) => (TokenKind::Equal, false),
+            ('!', _) => (TokenKind::Not, false),
+            ('<', _) => (TokenKind::LessThan, false),
+            ('>', _) => (TokenKind::GreaterThan, false),
+            // Single & and | aren't operators
+            _ => {
+                return Err(ErrorKind::UnexpectedToken(
+                    lexeme,
+                    self.span(start, start + 1),
+                ))
+            }
+        };
+
+        if is_double {
+            tokens.next();
+        }
+
+        Ok(kind)
+    }
+
+    fn lex_number<T: Iterator<Item = (usize, char)>>(
         &self,
         num: char,
+        start: usize,
         tokens: &mut Peekable<T>,
-    ) -> Result<Token> {
-        let mut num = num
-            .to_string()
-            .parse::<i32>()
-            .map_err(|_| ErrorKind::UnexpectedToken(num))?;
+    ) -> Result<TokenKind> {
+        if !num.is_ascii_digit() {
+            return Err(ErrorKind::UnexpectedToken(num, self.span(start, start + 1)));
+        }
 
-        while let Some(Ok(digit)) = tokens
-            .peek()
-            .map(|lexeme| lexeme.to_string().parse::<i32>())
-        {
-            num = (num * 10) + digit;
+        while tokens.next_if(|(_, lexeme)| lexeme.is_ascii_digit()).is_some() {}
 
-            tokens.next();
+        let end = self.offset(tokens);
+
+        let suffix = Lexer::float_suffix_len(&self.input[end..]);
+
+        if suffix == 0 {
+            let digits = &self.input[start..end];
+
+            // Numbers too big for an int are kept whole, rather than overflowing
+            return Ok(match digits.parse::<i32>() {
+                Ok(num) => TokenKind::Number(num),
+                Err(_) => TokenKind::BigNumber(
+                    digits
+                        .parse::<BigInt>()
+                        .map_err(|_| ErrorKind::UnexpectedToken(num, self.span(start, end)))?,
+                ),
+            });
         }
 
-        Ok(Token::Number(num))
+        // The suffix is all ascii, so each char is a byte
+        tokens.nth(suffix - 1);
+
+        // The suffix was checked above, so the lexeme is always a valid float
+        self.input[start..end + suffix]
+            .parse::<f64>()
+            .map(TokenKind::Float)
+            .map_err(|_| ErrorKind::UnexpectedToken('.', self.span(start, end + suffix)))
+    }
+
+    // The length of the fraction and exponent following the digits of a number, if it has them.
+    // Both need at least one digit, so that `1.` and `1e` aren't floats.
+    fn float_suffix_len(rest: &str) -> usize {
+        let digits = |rest: &str| rest.chars().take_while(char::is_ascii_digit).count();
+
+        let mut len = 0;
+
+        if let Some(fraction) = rest.strip_prefix('.') {
+            let fraction_digits = digits(fraction);
+
+            if fraction_digits > 0 {
+                len += 1 + fraction_digits;
+            }
+        }
+
+        if let Some(exponent) = rest[len..].strip_prefix(['e', 'E']) {
+            let sign = usize::from(exponent.starts_with(['+', '-']));
+
+            let exponent_digits = digits(&exponent[sign..]);
+
+            if exponent_digits > 0 {
+                len += 1 + sign + exponent_digits;
+            }
+        }
+
+        len
+    }
+
+    // Lexes the rest of a string literal after the opening quote. Strings can't span lines
+    fn lex_string<T: Iterator<Item = (usize, char)>>(
+        &self,
+        start: usize,
+        tokens: &mut Peekable<T>,
+    ) -> Result<TokenKind> {
+        let mut string = String::new();
+
+        loop {
+            let (offset, lexeme) = match tokens.next() {
+                Some((_, '\n')) | None => {
+                    return Err(ErrorKind::UnterminatedString(
+                        self.span(start, self.offset(tokens)),
+                    ))
+                }
+                Some(next) => next,
+            };
+
+            match lexeme {
+                '"' => return Ok(TokenKind::String(string)),
+                '\\' => {
+                    let escaped = match tokens.next() {
+                        Some((_, 'n')) => '\n',
+                        Some((_, 't')) => '\t',
+                        Some((_, 'r')) => '\r',
+                        Some((_, '0')) => '\0',
+                        Some((_, '\\')) => '\\',
+                        Some((_, '"')) => '"',
+                        Some((_, '\n')) | None => {
+                            return Err(ErrorKind::UnterminatedString(
+                                self.span(start, self.offset(tokens)),
+                            ))
+                        }
+                        Some((end, escaped)) => {
+                            return Err(ErrorKind::UnknownEscape(
+                                escaped,
+                                self.span(offset, end + escaped.len_utf8()),
+                            ))
+                        }
+                    };
+
+                    string.push(escaped);
+                }
+                _ => string.push(lexeme),
+            }
+        }
     }
 
-    fn lex_ident<T: Iterator<Item = char>>(
+    fn lex_ident<T: Iterator<Item = (usize, char)>>(
         &self,
         ident: char,
         tokens: &mut Peekable<T>,
@@ -120,7 +376,7 @@ impl Lexer {
         // Something like take_while would be here but it's not inclusive.
         let mut results = vec![];
 
-        while let Some(lexeme) = tokens.peek() {
+        while let Some((_, lexeme)) = tokens.peek() {
             if lexeme.is_alphanumeric() || *lexeme == '_' {
                 results.push(*lexeme);
             } else {
@@ -136,11 +392,115 @@ impl Lexer {
             results.into_iter().collect::<String>()
         ))
     }
+
+    // The byte offset of the next char, or the end of the input if there are none left
+    fn offset<T: Iterator<Item = (usize, char)>>(&self, tokens: &mut Peekable<T>) -> usize {
+        tokens
+            .peek()
+            .map(|(offset, _)| *offset)
+            .unwrap_or_else(|| self.input.len())
+    }
+
+    fn span(&self, start: usize, end: usize) -> Span {
+        let col = self.input[self.line_start..start].chars().count() + 1;
+
+        Span::new(start, end, self.line, col)
+    }
+
+    // Tokens don't run over lines, so the end is on the current line too
+    fn token(&self, kind: TokenKind, start: usize, end: usize) -> Token {
+        Token {
+            kind,
+            span: self.span(start, end),
+            end_col: self.input[self.line_start..end].chars().count() + 1,
+        }
+    }
+}
+
+impl ErrorKind {
+    // The character which couldn't start a token, the string from its opening quote to where the
+    // line or input ran out, or the backslash and the character after it
+    pub fn span(&self) -> Span {
+        match self {
+            ErrorKind::UnexpectedToken(_, span)
+            | ErrorKind::UnterminatedString(span)
+            | ErrorKind::UnknownEscape(_, span) => *span,
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::UnexpectedToken(lexeme, _) => {
+                write!(f, "unexpected character {:?}", lexeme)
+            }
+            ErrorKind::UnterminatedString(_) => write!(f, "unterminated string"),
+            ErrorKind::UnknownEscape(escaped, _) => {
+                write!(f, "unknown escape sequence \\{}", escaped)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ErrorKind {}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Number(num) => write!(f, "{}", num),
+            TokenKind::BigNumber(num) => write!(f, "{}", num),
+            TokenKind::Float(num) => write!(f, "{:?}", num),
+            TokenKind::String(string) => write!(f, "{:?}", string),
+            TokenKind::Ident(ident) => write!(f, "{}", ident),
+            TokenKind::Let => write!(f, "let"),
+            TokenKind::Print => write!(f, "print"),
+            TokenKind::True => write!(f, "true"),
+            TokenKind::False => write!(f, "false"),
+            TokenKind::Nil => write!(f, "nil"),
+            TokenKind::If => write!(f, "if"),
+            TokenKind::Else => write!(f, "else"),
+            TokenKind::While => write!(f, "while"),
+            TokenKind::Fn => write!(f, "fn"),
+            TokenKind::Return => write!(f, "return"),
+            TokenKind::LBracket => write!(f, "("),
+            TokenKind::RBracket => write!(f, ")"),
+            TokenKind::LBrace => write!(f, "{{"),
+            TokenKind::RBrace => write!(f, "}}"),
+            TokenKind::LSquare => write!(f, "["),
+            TokenKind::RSquare => write!(f, "]"),
+            TokenKind::Comma => write!(f, ","),
+            TokenKind::Colon => write!(f, ":"),
+            TokenKind::Plus => write!(f, "+"),
+            TokenKind::Minus => write!(f, "-"),
+            TokenKind::Times => write!(f, "*"),
+            TokenKind::Divide => write!(f, "/"),
+            TokenKind::Modulo => write!(f, "%"),
+            TokenKind::Equal => write!(f, "="),
+            TokenKind::DoubleEqual => write!(f, "=="),
+            TokenKind::NotEqual => write!(f, "!="),
+            TokenKind::LessThan => write!(f, "<"),
+            TokenKind::LessThanOrEqual => write!(f, "<="),
+            TokenKind::GreaterThan => write!(f, ">"),
+            TokenKind::GreaterThanOrEqual => write!(f, ">="),
+            TokenKind::And => write!(f, "&&"),
+            TokenKind::Or => write!(f, "||"),
+            TokenKind::Not => write!(f, "!"),
+            TokenKind::Semicolon => write!(f, ";"),
+            TokenKind::Newline => write!(f, "newline"),
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{Lexer, Token, Result};
+    use crate::span::Span;
+
+    use super::{ErrorKind, Lexer, Result, Token, TokenKind};
+
+    fn kinds(tokens: Vec<Token>) -> Vec<TokenKind> {
+        tokens.into_iter().map(|token| token.kind).collect()
+    }
 
     macro_rules! lex_single_char_token {
         ($token_name:ident, $token:expr, $token_symbol:expr) => {
@@ -148,58 +508,281 @@ mod test {
             fn $token_name() -> Result {
                 let mut lexer = Lexer::new($token_symbol); 
 
-                assert_eq!(lexer.run()?, vec![$token]);
+                assert_eq!(kinds(lexer.run()?), vec![$token]);
 
                 Ok(())
             }
         };
     }
 
-    lex_single_char_token!(lexing_lbracket_works, Token::LBracket, "(");
+    lex_single_char_token!(lexing_lbracket_works, TokenKind::LBracket, "(");
+
+    lex_single_char_token!(lexing_rbracket_works, TokenKind::RBracket, ")");
+
+    lex_single_char_token!(lexing_lbrace_works, TokenKind::LBrace, "{");
+
+    lex_single_char_token!(lexing_rbrace_works, TokenKind::RBrace, "}");
+
+    lex_single_char_token!(lexing_lsquare_works, TokenKind::LSquare, "[");
+
+    lex_single_char_token!(lexing_rsquare_works, TokenKind::RSquare, "]");
+
+    lex_single_char_token!(lexing_comma_works, TokenKind::Comma, ",");
+
+    lex_single_char_token!(lexing_colon_works, TokenKind::Colon, ":");
+
+    lex_single_char_token!(lexing_plus_works, TokenKind::Plus, "+");
+
+    lex_single_char_token!(lexing_minus_works, TokenKind::Minus, "-");
+
+    lex_single_char_token!(lexing_times_works, TokenKind::Times, "*");
+
+    lex_single_char_token!(lexing_divide_works, TokenKind::Divide, "/");
+
+    lex_single_char_token!(lexing_eq_works, TokenKind::Equal, "=");
+
+    lex_single_char_token!(lexing_modulo_works, TokenKind::Modulo, "%");
+
+    lex_single_char_token!(lexing_not_works, TokenKind::Not, "!");
+
+    lex_single_char_token!(lexing_less_than_works, TokenKind::LessThan, "<");
 
-    lex_single_char_token!(lexing_rbracket_works, Token::RBracket, ")");
+    lex_single_char_token!(lexing_greater_than_works, TokenKind::GreaterThan, ">");
 
-    lex_single_char_token!(lexing_plus_works, Token::Plus, "+");
+    lex_single_char_token!(lexing_double_eq_works, TokenKind::DoubleEqual, "==");
 
-    lex_single_char_token!(lexing_minus_works, Token::Minus, "-");
+    lex_single_char_token!(lexing_not_eq_works, TokenKind::NotEqual, "!=");
 
-    lex_single_char_token!(lexing_times_works, Token::Times, "*");
+    lex_single_char_token!(lexing_less_than_eq_works, TokenKind::LessThanOrEqual, "<=");
 
-    lex_single_char_token!(lexing_divide_works, Token::Divide, "/");
+    lex_single_char_token!(
+        lexing_greater_than_eq_works,
+        TokenKind::GreaterThanOrEqual,
+        ">="
+    );
 
-    lex_single_char_token!(lexing_eq_works, Token::Equal, "=");
+    lex_single_char_token!(lexing_and_works, TokenKind::And, "&&");
+
+    lex_single_char_token!(lexing_or_works, TokenKind::Or, "||");
+
+    lex_single_char_token!(lexing_semicolon_works, TokenKind::Semicolon, ";");
+
+    lex_single_char_token!(lexing_newline_works, TokenKind::Newline, "\n");
+
+    #[test]
+    fn lexing_keywords_works() -> Result {
+        let mut let_lexer = Lexer::new("let");
+
+        let mut print_lexer = Lexer::new("print");
+
+        assert_eq!(kinds(let_lexer.run()?), vec![TokenKind::Let]);
+
+        assert_eq!(kinds(print_lexer.run()?), vec![TokenKind::Print]);
+
+        assert_eq!(
+            kinds(Lexer::new("true false nil").run()?),
+            vec![TokenKind::True, TokenKind::False, TokenKind::Nil]
+        );
+
+        assert_eq!(
+            kinds(Lexer::new("if else while fn return").run()?),
+            vec![
+                TokenKind::If,
+                TokenKind::Else,
+                TokenKind::While,
+                TokenKind::Fn,
+                TokenKind::Return
+            ]
+        );
+
+        Ok(())
+    }
 
     #[test]
     fn lexing_numbers_works() -> Result {
-        let mut single_num_lexer = Lexer::new(&"1");
+        let mut single_num_lexer = Lexer::new("1");
+
+        let mut multi_num_lexer = Lexer::new("3213");
 
-        let mut multi_num_lexer = Lexer::new(&"3213");
+        assert_eq!(kinds(single_num_lexer.run()?), vec![TokenKind::Number(1)]);
 
-        assert_eq!(single_num_lexer.run()?, vec![Token::Number(1)]);
+        assert_eq!(kinds(multi_num_lexer.run()?), vec![TokenKind::Number(3213)]);
 
-        assert_eq!(multi_num_lexer.run()?, vec![Token::Number(3213)]);
+        let mut big_num_lexer = Lexer::new("2147483648");
+
+        assert_eq!(
+            kinds(big_num_lexer.run()?),
+            vec![TokenKind::BigNumber("2147483648".parse().unwrap())]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn lexing_floats_works() -> Result {
+        let mut lexer = Lexer::new("1.5 2e10 0.25E-2 3e+1");
+
+        assert_eq!(
+            kinds(lexer.run()?),
+            vec![
+                TokenKind::Float(1.5),
+                TokenKind::Float(2e10),
+                TokenKind::Float(0.25e-2),
+                TokenKind::Float(30.0)
+            ]
+        );
+
+        let mut not_floats = Lexer::new("1e x");
+
+        assert_eq!(
+            kinds(not_floats.run()?),
+            vec![
+                TokenKind::Number(1),
+                TokenKind::Ident("e".to_string()),
+                TokenKind::Ident("x".to_string())
+            ]
+        );
+
+        assert!(Lexer::new("1.").run().is_err());
 
         Ok(())
     }
 
     #[test]
     fn lexing_ident_works() -> Result {
-        let mut single_char_lexer = Lexer::new(&"x");
+        let mut single_char_lexer = Lexer::new("x");
 
-        let mut multi_char_lexer = Lexer::new(&"heLLo");
+        let mut multi_char_lexer = Lexer::new("heLLo");
 
-        let mut must_not_start_with_num_lexer = Lexer::new(&"3ff");
+        let mut must_not_start_with_num_lexer = Lexer::new("3ff");
 
         let mut can_contain_underscores = Lexer::new("x_y_z");
 
-        assert_eq!(single_char_lexer.run()?, vec![Token::Ident("x".to_string())]);
+        assert_eq!(kinds(single_char_lexer.run()?), vec![TokenKind::Ident("x".to_string())]);
 
-        assert_eq!(multi_char_lexer.run()?, vec![Token::Ident("heLLo".to_string())]);
+        assert_eq!(kinds(multi_char_lexer.run()?), vec![TokenKind::Ident("heLLo".to_string())]);
 
-        assert_eq!(must_not_start_with_num_lexer.run()?, vec![Token::Number(3), Token::Ident("ff".to_string())]); 
+        assert_eq!(kinds(must_not_start_with_num_lexer.run()?), vec![TokenKind::Number(3), TokenKind::Ident("ff".to_string())]); 
 
-        assert_eq!(can_contain_underscores.run()?, vec![Token::Ident("x_y_z".to_string())]); 
+        assert_eq!(kinds(can_contain_underscores.run()?), vec![TokenKind::Ident("x_y_z".to_string())]); 
 
         Ok(())
     }
+
+    #[test]
+    fn lexing_strings_works() -> Result {
+        let mut empty_lexer = Lexer::new(r#""""#);
+
+        let mut escape_lexer = Lexer::new(r#""say \"hi\"\n\t\\ ok""#);
+
+        assert_eq!(
+            kinds(empty_lexer.run()?),
+            vec![TokenKind::String("".to_string())]
+        );
+
+        assert_eq!(
+            kinds(escape_lexer.run()?),
+            vec![TokenKind::String("say \"hi\"\n\t\\ ok".to_string())]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn lexing_bad_strings_fails() {
+        let unterminated = Lexer::new("let x = \"abc\nprint x").run().unwrap_err();
+
+        let unknown_escape = Lexer::new(r#""a\qb""#).run().unwrap_err();
+
+        assert!(matches!(unterminated, ErrorKind::UnterminatedString(_)));
+
+        assert_eq!(unterminated.span(), Span::new(8, 13, 1, 9));
+
+        assert!(matches!(unknown_escape, ErrorKind::UnknownEscape('q', _)));
+
+        assert_eq!(unknown_escape.span(), Span::new(2, 4, 1, 3));
+    }
+
+    #[test]
+    fn lexing_spans_works() -> Result {
+        let mut lexer = Lexer::new("let xy = 42");
+
+        let spans = lexer
+            .run()?
+            .into_iter()
+            .map(|token| token.span)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            spans,
+            vec![
+                Span::new(0, 3, 1, 1),
+                Span::new(4, 6, 1, 5),
+                Span::new(7, 8, 1, 8),
+                Span::new(9, 11, 1, 10),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn lexing_newlines_in_brackets_is_skipped() -> Result {
+        let mut lexer = Lexer::new("(1\n)\n");
+
+        assert_eq!(
+            kinds(lexer.run()?),
+            vec![
+                TokenKind::LBracket,
+                TokenKind::Number(1),
+                TokenKind::RBracket,
+                TokenKind::Newline
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn lexing_newlines_in_braces_inside_brackets_is_kept() -> Result {
+        let mut lexer = Lexer::new("({\n})");
+
+        assert_eq!(
+            kinds(lexer.run()?),
+            vec![
+                TokenKind::LBracket,
+                TokenKind::LBrace,
+                TokenKind::Newline,
+                TokenKind::RBrace,
+                TokenKind::RBracket
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn lexing_spans_tracks_lines() -> Result {
+        let mut lexer = Lexer::new("let x = 1\n  print x");
+
+        let tokens = lexer.run()?;
+
+        assert_eq!(tokens[4].span, Span::new(9, 10, 1, 10));
+
+        assert_eq!(tokens[5].span, Span::new(12, 17, 2, 3));
+
+        Ok(())
+    }
+
+    #[test]
+    fn lexing_single_ampersand_fails() {
+        assert!(Lexer::new("1 & 2").run().is_err());
+    }
+
+    #[test]
+    fn lexing_unexpected_token_reports_span() {
+        let mut lexer = Lexer::new("let x = $");
+
+        assert_eq!(lexer.run().unwrap_err().span(), Span::new(8, 9, 1, 9));
+    }
 }
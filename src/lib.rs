@@ -0,0 +1,108 @@
+mod ast;
+mod compiler;
+mod engine;
+mod lexer;
+mod parser;
+mod repl;
+mod span;
+#[cfg(test)]
+mod testing;
+mod vm;
+
+use std::{fmt, io};
+
+// What embedders need to run scripts, pass values in and out, and match on errors. Functions and
+// natives are only handled through Value, and instructions and tokens aren't exposed at all.
+pub use crate::{
+    compiler::ErrorKind as CompilerErrorKind,
+    engine::Engine,
+    lexer::ErrorKind as LexerErrorKind,
+    parser::ErrorKind as ParserErrorKind,
+    repl::Repl,
+    span::Span,
+    vm::{
+        bigint::BigInt,
+        inter::Inter,
+        native::NativeFn,
+        stack::{Limits, StackErrorKind, StackKind},
+        trace::{TraceFrame, Traceback},
+        value::{MapKey, Value},
+        ErrorKind as VmErrorKind,
+    },
+};
+
+pub type Result<T = ()> = std::result::Result<T, ErrorKind>;
+
+#[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    VmError(VmErrorKind, Traceback),
+    LexerError(LexerErrorKind),
+    ParserError(Vec<ParserErrorKind>),
+    CompilerError(CompilerErrorKind),
+    IoError(io::Error),
+}
+
+impl ErrorKind {
+    // Each kind of error exits with its own status, so scripts running the CLI can tell them apart
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ErrorKind::IoError(_) => 1,
+            ErrorKind::LexerError(_) => 2,
+            ErrorKind::ParserError(_) => 3,
+            ErrorKind::VmError(..) => 4,
+            ErrorKind::CompilerError(_) => 5,
+        }
+    }
+
+    // Lexer, parser and compiler errors point into the source they came from. Runtime errors list
+    // the active calls instead, as the functions may have come from earlier sources.
+    pub fn render(&self, source: &str) -> String {
+        match self {
+            ErrorKind::LexerError(err) => err.span().render(source, &err.to_string()),
+            ErrorKind::ParserError(errs) => errs
+                .iter()
+                .map(|err| err.span().render(source, &err.to_string()))
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+            ErrorKind::CompilerError(err) => err.span().render(source, &err.to_string()),
+            ErrorKind::VmError(err, traceback) if traceback.frames.is_empty() => {
+                format!("error: {}", err)
+            }
+            ErrorKind::VmError(err, traceback) => format!("error: {}\n{}", err, traceback),
+            ErrorKind::IoError(err) => format!("error: {}", err),
+        }
+    }
+}
+
+// The kind of error and its message, without the source line or traceback render adds. Of several
+// syntax errors only the first is described.
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::VmError(err, _) => write!(f, "runtime error: {}", err),
+            ErrorKind::LexerError(err) => write!(f, "invalid token: {}", err),
+            ErrorKind::ParserError(errs) => match errs.as_slice() {
+                [] => write!(f, "syntax error"),
+                [err] => write!(f, "syntax error: {}", err),
+                [err, ..] => write!(f, "{} syntax errors, the first: {}", errs.len(), err),
+            },
+            ErrorKind::CompilerError(err) => write!(f, "compile error: {}", err),
+            ErrorKind::IoError(err) => write!(f, "i/o error: {}", err),
+        }
+    }
+}
+
+// The source of a parser error is the first one, the rest can be had from the variant
+impl std::error::Error for ErrorKind {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ErrorKind::VmError(err, _) => Some(err),
+            ErrorKind::LexerError(err) => Some(err),
+            ErrorKind::ParserError(errs) => errs.first().map(|err| err as _),
+            ErrorKind::CompilerError(err) => Some(err),
+            ErrorKind::IoError(err) => Some(err),
+        }
+    }
+}
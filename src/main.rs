@@ -1,82 +1,98 @@
-mod ast;
-mod lexer;
-mod parser;
-mod vm;
-
-use crate::vm::{
-    instr::{BinopKind, CompareKind, Instr},
-    inter::Inter,
-    value::Value,
-    ErrorKind as VmErrorKind,
+use std::{
+    env, fs,
+    io::{self, Read},
+    process,
 };
 
-use crate::{
-    lexer::{ErrorKind as LexerErrorKind, Lexer},
-    parser::{ErrorKind as ParserErrorKind, Parser},
-};
+use inter::{Engine, ErrorKind, Limits, Repl, Result};
+
+const USAGE: &str =
+    "usage: inter [--max-values N] [--max-frames N] [--max-scopes N] <script | - | repl>";
+
+// Exit status used when the command line itself is malformed
+const USAGE_EXIT_CODE: i32 = 64;
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    let (limits, mode) = match parse_args(&args) {
+        Some(parsed) => parsed,
+        None => {
+            eprintln!("{}", USAGE);
+
+            process::exit(USAGE_EXIT_CODE);
+        }
+    };
+
+    let (source, result) = match mode {
+        "repl" => (
+            String::new(),
+            Repl::new(limits).and_then(|mut repl| repl.run()),
+        ),
+        path => match read_source(path) {
+            Ok(source) => {
+                let result = run(&source, limits);
+
+                (source, result)
+            }
+            Err(err) => (String::new(), Err(err)),
+        },
+    };
+
+    if let Err(err) = result {
+        eprintln!("{}", err.render(&source));
+
+        process::exit(err.exit_code());
+    }
+}
 
-type Result<T = ()> = std::result::Result<T, ErrorKind>;
+// Splits the stack limits from the mode, or None if the command line is malformed
+fn parse_args(args: &[String]) -> Option<(Limits, &str)> {
+    let mut limits = Limits::default();
+
+    let mut mode = None;
+
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let limit = match arg.as_str() {
+            "--max-values" => &mut limits.values,
+            "--max-frames" => &mut limits.frames,
+            "--max-scopes" => &mut limits.scopes,
+            _ if mode.is_none() => {
+                mode = Some(arg.as_str());
+
+                continue;
+            }
+            _ => return None,
+        };
+
+        *limit = args.next()?.parse().ok()?;
+    }
+
+    mode.map(|mode| (limits, mode))
+}
+
+fn read_source(path: &str) -> Result<String> {
+    // A path of "-" reads the script from stdin
+    if path == "-" {
+        let mut source = String::new();
+
+        io::stdin()
+            .read_to_string(&mut source)
+            .map_err(|err| io_error("<stdin>", err))?;
+
+        Ok(source)
+    } else {
+        fs::read_to_string(path).map_err(|err| io_error(path, err))
+    }
+}
 
-#[derive(Debug)]
-pub(crate) enum ErrorKind {
-    VmError(VmErrorKind),
-    LexerError(LexerErrorKind),
-    ParserError(ParserErrorKind),
+// The error is prefixed with the path, so it's clear which script couldn't be read
+fn io_error(path: &str, err: io::Error) -> ErrorKind {
+    ErrorKind::IoError(io::Error::new(err.kind(), format!("{}: {}", path, err)))
 }
 
-fn main() -> Result {
-    /*
-    let mut lexer = Lexer::new("let example = 100+100*200/300");
-
-    let parser = Parser::new(lexer.run().map_err(|err| ErrorKind::LexerError(err))?);
-
-    let parse = parser.parse().map_err(|err| ErrorKind::ParserError(err))?;
-
-    println!("{:?}", parse);
-
-    Ok(())
-    */
-
-    let mut inter = Inter::new().map_err(|err| ErrorKind::VmError(err))?;
-
-    /*
-     * i = 0
-     *
-     * while i < 3:
-     *     print(i)
-     *
-     *     i += 1
-     */
-    inter.push_instrs(&[
-        // i = 0
-        Instr::Push(Value::Int(0)),
-        Instr::Store("i".into()),
-        // while i != 3
-        Instr::Load("i".into()),
-        Instr::Push(Value::Int(3)),
-        Instr::Compare(CompareKind::GreaterThan),
-        Instr::PopJumpFalse(13),
-        // print i
-        Instr::Load("i".into()),
-        Instr::Print,
-        // i += 1
-        Instr::Load("i".into()),
-        Instr::Push(Value::Int(1)),
-        Instr::Binop(BinopKind::Plus),
-        Instr::Store("i".into()),
-        // to start of loop
-        Instr::Jump(2),
-        Instr::Exit,
-    ]);
-
-    /*
-    inter.push_instrs(&[
-        Instr::Push(Value::Int(400)),
-        Instr::Store("x".into()),
-        Instr::Load("x".into()),
-        Instr::Print,
-    ]);
-    */
-
-    inter.run().map_err(|err| ErrorKind::VmError(err))
+fn run(source: &str, limits: Limits) -> Result {
+    Engine::with_limits(limits)?.eval(source).map(|_| ())
 }
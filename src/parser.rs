@@ -1,111 +1,1147 @@
+use std::{cell::RefCell, fmt};
+
 use crate::{
-    ast::{BinopKind, Expr, Stmt},
-    lexer::Token,
+    ast::{BinopKind, Expr, ExprKind, Stmt, StmtKind, UnaryKind},
+    lexer::{Token, TokenKind},
+    span::Span,
 };
 
 pub(crate) type Result<T = ()> = std::result::Result<T, ErrorKind>;
 
 #[derive(Clone, Debug)]
-pub(crate) enum ErrorKind {
-    UnexpectedToken(Token),
-    UnexpectedEndOfInput(usize),
+#[non_exhaustive]
+pub enum ErrorKind {
+    // The token as it's written in the source
+    UnexpectedToken(String, Span),
+    UnexpectedEndOfLine(Span),
+    UnexpectedEndOfInput(Span),
 }
 
 pub(crate) struct Parser {
     tokens: Vec<Token>,
+
+    // Errors that were recovered from, so parsing carried on past them
+    errors: RefCell<Vec<ErrorKind>>,
 }
 
 impl Parser {
     pub(crate) fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens }
+        Self {
+            tokens,
+            errors: RefCell::new(vec![]),
+        }
     }
 
-    pub(crate) fn parse(&self) -> Result<Vec<Stmt>> {
-        let mut exprs = vec![];
+    // Parses every statement, carrying on after a syntax error so that they can all be reported
+    pub(crate) fn parse(&self) -> std::result::Result<Vec<Stmt>, Vec<ErrorKind>> {
+        let mut stmts = vec![];
+
+        let mut pos = 0;
+
+        // Statements are only parsed up to a closing brace, which is out of place at the top level
+        while pos < self.tokens.len() {
+            let (parsed, end) = self.parse_stmts(&self.tokens, pos);
 
-        let mut parsed = self.parse_binding(&self.tokens, 0);
+            stmts.extend(parsed);
 
-        while let Ok((expr, pos)) = parsed {
-            exprs.push(expr);
+            if end < self.tokens.len() {
+                self.errors
+                    .borrow_mut()
+                    .push(self.unexpected(&self.tokens, end));
+            }
 
-            parsed = self.parse_binding(&self.tokens, pos);
+            pos = end + 1;
         }
 
-        Ok(exprs)
+        let errors = self.errors.take();
+
+        if errors.is_empty() {
+            Ok(stmts)
+        } else {
+            Err(errors)
+        }
     }
 
-    fn parse_binding(&self, tokens: &[Token], pos: usize) -> Result<(Stmt, usize)> {
-        match (tokens.get(pos), tokens.get(pos + 1), tokens.get(pos + 2)) {
-            (Some(Token::Let), Some(Token::Ident(name)), Some(Token::Equal)) => {
-                let (expr, pos) = self.parse_expr(tokens, pos + 3)?;
+    // Parses statements until the end of the input or a closing brace, recovering from errors
+    fn parse_stmts(&self, tokens: &[Token], pos: usize) -> (Vec<Stmt>, usize) {
+        let mut stmts = vec![];
+
+        let mut pos = self.skip_separators(tokens, pos);
 
-                Ok((Stmt::Binding(name.to_string(), expr), pos))
+        while let Some(kind) = self.kind(tokens, pos) {
+            if *kind == TokenKind::RBrace {
+                break;
+            }
+
+            let parsed = self
+                .parse_stmt(tokens, pos)
+                .and_then(|(stmt, end)| Ok((stmt, self.parse_separator(tokens, end)?)));
+
+            match parsed {
+                Ok((stmt, end)) => {
+                    stmts.push(stmt);
+
+                    pos = self.skip_separators(tokens, end);
+                }
+                Err(err) => {
+                    self.errors.borrow_mut().push(err);
+
+                    pos = self.skip_separators(tokens, self.synchronise(tokens, pos + 1));
+                }
             }
-            _ => Err(ErrorKind::UnexpectedEndOfInput(pos)) 
         }
+
+        (stmts, pos)
     }
 
-    fn parse_expr(&self, tokens: &[Token], pos: usize) -> Result<(Expr, usize)> {
-        let (left, pos) = self.parse_term(tokens, pos)?;
+    /*
+     * Skips to where the next statement is likely to start, which is after a separator or at a
+     * keyword. Whole blocks are skipped, stopping before a closing brace of an enclosing block.
+     */
+    fn synchronise(&self, tokens: &[Token], mut pos: usize) -> usize {
+        let mut depth = 0;
 
-        match tokens.get(pos) {
-            Some(Token::Plus) => {
-                let (right, pos) = self.parse_term(tokens, pos + 1)?;
+        while let Some(kind) = self.kind(tokens, pos) {
+            match kind {
+                TokenKind::LBrace => depth += 1,
+                TokenKind::RBrace if depth == 0 => return pos,
+                TokenKind::RBrace => depth -= 1,
+                TokenKind::Newline | TokenKind::Semicolon if depth == 0 => return pos + 1,
+                TokenKind::Let
+                | TokenKind::Print
+                | TokenKind::If
+                | TokenKind::While
+                | TokenKind::Fn
+                | TokenKind::Return
+                    if depth == 0 =>
+                {
+                    return pos
+                }
+                _ => {}
+            }
+
+            pos += 1;
+        }
+
+        pos
+    }
+
+    // Statements end at a newline or a semicolon, or at the end of the input or a block
+    fn parse_separator(&self, tokens: &[Token], pos: usize) -> Result<usize> {
+        match self.kind(tokens, pos) {
+            Some(TokenKind::Newline) | Some(TokenKind::Semicolon) => Ok(pos + 1),
+            Some(TokenKind::RBrace) | None => Ok(pos),
+            _ => Err(self.unexpected(tokens, pos)),
+        }
+    }
+
+    // Blank lines and stray semicolons between statements are ignored
+    fn skip_separators(&self, tokens: &[Token], mut pos: usize) -> usize {
+        while let Some(TokenKind::Newline) | Some(TokenKind::Semicolon) = self.kind(tokens, pos) {
+            pos += 1;
+        }
+
+        pos
+    }
+
+    fn parse_stmt(&self, tokens: &[Token], pos: usize) -> Result<(Stmt, usize)> {
+        match self.kind(tokens, pos) {
+            Some(TokenKind::Print) => self.parse_print(tokens, pos),
+            Some(TokenKind::Let) => self.parse_binding(tokens, pos),
+            Some(TokenKind::If) => self.parse_if(tokens, pos),
+            Some(TokenKind::While) => self.parse_while(tokens, pos),
+            // Without a name the function is an expression
+            Some(TokenKind::Fn)
+                if matches!(self.kind(tokens, pos + 1), Some(TokenKind::Ident(_))) =>
+            {
+                self.parse_function(tokens, pos)
+            }
+            Some(TokenKind::Return) => self.parse_return(tokens, pos),
+            Some(TokenKind::Ident(_)) if self.kind(tokens, pos + 1) == Some(&TokenKind::Equal) => {
+                self.parse_assign(tokens, pos)
+            }
+            _ => {
+                let (expr, pos) = self.parse_expr(tokens, pos)?;
+
+                if self.kind(tokens, pos) == Some(&TokenKind::Equal) {
+                    return self.parse_index_assign(expr, tokens, pos);
+                }
+
+                let span = expr.span;
 
                 Ok((
-                    Expr::Binop(BinopKind::Plus, Box::new(left), Box::new(right)),
+                    Stmt {
+                        kind: StmtKind::Expr(expr),
+                        span,
+                    },
                     pos,
                 ))
             }
-            Some(Token::Minus) => {
-                let (right, pos) = self.parse_term(tokens, pos + 1)?;
+        }
+    }
+
+    // Only an index can be assigned to, other than a name, like xs[0] = 1
+    fn parse_index_assign(
+        &self,
+        target: Expr,
+        tokens: &[Token],
+        pos: usize,
+    ) -> Result<(Stmt, usize)> {
+        let (list, index) = match target.kind {
+            ExprKind::Index(list, index) => (*list, *index),
+            _ => return Err(self.unexpected(tokens, pos)),
+        };
+
+        let (expr, end) = self.parse_expr(tokens, pos + 1)?;
+
+        let span = target.span.to(expr.span);
+
+        Ok((
+            Stmt {
+                kind: StmtKind::IndexAssign(list, index, expr),
+                span,
+            },
+            end,
+        ))
+    }
+
+    fn parse_function(&self, tokens: &[Token], pos: usize) -> Result<(Stmt, usize)> {
+        let name = match self.kind(tokens, pos + 1) {
+            Some(TokenKind::Ident(name)) => name.to_string(),
+            _ => return Err(self.unexpected(tokens, pos + 1)),
+        };
+
+        let (params, body, end) = self.parse_params_and_body(tokens, pos + 2)?;
+
+        Ok((
+            Stmt {
+                kind: StmtKind::Function(name, params, body),
+                span: tokens[pos].span.to(tokens[end - 1].span),
+            },
+            end,
+        ))
+    }
+
+    fn parse_anonymous_function(&self, tokens: &[Token], pos: usize) -> Result<(Expr, usize)> {
+        let (params, body, end) = self.parse_params_and_body(tokens, pos + 1)?;
+
+        Ok((
+            Expr {
+                kind: ExprKind::Function(params, body),
+                span: tokens[pos].span.to(tokens[end - 1].span),
+            },
+            end,
+        ))
+    }
+
+    // The bracketed parameters and the block which follow the name of a function, if it has one
+    fn parse_params_and_body(
+        &self,
+        tokens: &[Token],
+        pos: usize,
+    ) -> Result<(Vec<String>, Vec<Stmt>, usize)> {
+        if self.kind(tokens, pos) != Some(&TokenKind::LBracket) {
+            return Err(self.unexpected(tokens, pos));
+        }
+
+        let (params, end) = self.parse_list(
+            tokens,
+            pos + 1,
+            TokenKind::RBracket,
+            |tokens, pos| match self.kind(tokens, pos) {
+                Some(TokenKind::Ident(param)) => Ok((param.to_string(), pos + 1)),
+                _ => Err(self.unexpected(tokens, pos)),
+            },
+        )?;
+
+        let (body, end) = self.parse_block(tokens, end)?;
+
+        Ok((params, body, end))
+    }
+
+    fn parse_return(&self, tokens: &[Token], pos: usize) -> Result<(Stmt, usize)> {
+        // Nothing after the return, up to the end of the statement, returns nil
+        if let Some(TokenKind::Newline | TokenKind::Semicolon | TokenKind::RBrace) | None =
+            self.kind(tokens, pos + 1)
+        {
+            return Ok((
+                Stmt {
+                    kind: StmtKind::Return(None),
+                    span: tokens[pos].span,
+                },
+                pos + 1,
+            ));
+        }
+
+        let (expr, end) = self.parse_expr(tokens, pos + 1)?;
+
+        let span = tokens[pos].span.to(expr.span);
+
+        Ok((
+            Stmt {
+                kind: StmtKind::Return(Some(expr)),
+                span,
+            },
+            end,
+        ))
+    }
+
+    fn parse_print(&self, tokens: &[Token], pos: usize) -> Result<(Stmt, usize)> {
+        let (expr, end) = self.parse_expr(tokens, pos + 1)?;
+
+        let span = tokens[pos].span.to(expr.span);
+
+        Ok((
+            Stmt {
+                kind: StmtKind::Print(expr),
+                span,
+            },
+            end,
+        ))
+    }
+
+    fn parse_assign(&self, tokens: &[Token], pos: usize) -> Result<(Stmt, usize)> {
+        let name = match self.kind(tokens, pos) {
+            Some(TokenKind::Ident(name)) => name.to_string(),
+            _ => return Err(self.unexpected(tokens, pos)),
+        };
+
+        let (expr, end) = self.parse_expr(tokens, pos + 2)?;
+
+        let span = tokens[pos].span.to(expr.span);
+
+        Ok((
+            Stmt {
+                kind: StmtKind::Assign(name, expr),
+                span,
+            },
+            end,
+        ))
+    }
+
+    fn parse_if(&self, tokens: &[Token], pos: usize) -> Result<(Stmt, usize)> {
+        let (cond, end) = self.parse_expr(tokens, pos + 1)?;
+
+        let (then_block, mut end) = self.parse_block(tokens, end)?;
+
+        // The else may be on the line after the closing brace
+        let else_pos = self.skip_newlines(tokens, end);
+
+        let else_block = match (self.kind(tokens, else_pos), self.kind(tokens, else_pos + 1)) {
+            // An else if is an else block containing only the if statement
+            (Some(TokenKind::Else), Some(TokenKind::If)) => {
+                let (stmt, else_end) = self.parse_if(tokens, else_pos + 1)?;
+
+                end = else_end;
+
+                Some(vec![stmt])
+            }
+            (Some(TokenKind::Else), _) => {
+                let (block, else_end) = self.parse_block(tokens, else_pos + 1)?;
+
+                end = else_end;
+
+                Some(block)
+            }
+            _ => None,
+        };
+
+        Ok((
+            Stmt {
+                kind: StmtKind::If(cond, then_block, else_block),
+                span: tokens[pos].span.to(tokens[end - 1].span),
+            },
+            end,
+        ))
+    }
+
+    fn parse_while(&self, tokens: &[Token], pos: usize) -> Result<(Stmt, usize)> {
+        let (cond, end) = self.parse_expr(tokens, pos + 1)?;
+
+        let (block, end) = self.parse_block(tokens, end)?;
+
+        Ok((
+            Stmt {
+                kind: StmtKind::While(cond, block),
+                span: tokens[pos].span.to(tokens[end - 1].span),
+            },
+            end,
+        ))
+    }
+
+    // Parses statements between braces, returning the position after the closing brace
+    fn parse_block(&self, tokens: &[Token], pos: usize) -> Result<(Vec<Stmt>, usize)> {
+        if self.kind(tokens, pos) != Some(&TokenKind::LBrace) {
+            return Err(self.unexpected(tokens, pos));
+        }
+
+        let (stmts, end) = self.parse_stmts(tokens, pos + 1);
+
+        match self.kind(tokens, end) {
+            Some(TokenKind::RBrace) => Ok((stmts, end + 1)),
+            _ => Err(self.unexpected(tokens, end)),
+        }
+    }
+
+    fn skip_newlines(&self, tokens: &[Token], mut pos: usize) -> usize {
+        while let Some(TokenKind::Newline) = self.kind(tokens, pos) {
+            pos += 1;
+        }
+
+        pos
+    }
+
+    fn parse_binding(&self, tokens: &[Token], pos: usize) -> Result<(Stmt, usize)> {
+        match (
+            self.kind(tokens, pos),
+            self.kind(tokens, pos + 1),
+            self.kind(tokens, pos + 2),
+        ) {
+            (Some(TokenKind::Let), Some(TokenKind::Ident(name)), Some(TokenKind::Equal)) => {
+                let (expr, end) = self.parse_expr(tokens, pos + 3)?;
+
+                let span = tokens[pos].span.to(expr.span);
 
                 Ok((
-                    Expr::Binop(BinopKind::Minus, Box::new(left), Box::new(right)),
-                    pos,
+                    Stmt {
+                        kind: StmtKind::Binding(name.to_string(), expr),
+                        span,
+                    },
+                    end,
                 ))
             }
-            _ => Ok((left, pos)),
+            (Some(TokenKind::Let), Some(TokenKind::Ident(_)), _) => {
+                Err(self.unexpected(tokens, pos + 2))
+            }
+            (Some(TokenKind::Let), _, _) => Err(self.unexpected(tokens, pos + 1)),
+            _ => Err(self.unexpected(tokens, pos)),
         }
     }
 
-    fn parse_term(&self, tokens: &[Token], pos: usize) -> Result<(Expr, usize)> {
-        let (left, pos) = self.parse_literal(tokens, pos)?;
+    fn parse_expr(&self, tokens: &[Token], pos: usize) -> Result<(Expr, usize)> {
+        self.parse_binop(tokens, pos, 0)
+    }
 
-        match tokens.get(pos) {
-            Some(Token::Times) => {
-                let (right, pos) = self.parse_literal(tokens, pos + 1)?;
+    // Precedence climbing, only operators binding at least as tightly as min_prec are parsed
+    fn parse_binop(&self, tokens: &[Token], pos: usize, min_prec: u8) -> Result<(Expr, usize)> {
+        let (mut left, mut pos) = self.parse_unary(tokens, pos)?;
+
+        while let Some((kind, prec)) = self.kind(tokens, pos).and_then(Parser::binop_kind) {
+            if prec < min_prec {
+                break;
+            }
+
+            // Every operator is left associative, so the right hand side must bind tighter
+            let (right, end) = self.parse_binop(tokens, pos + 1, prec + 1)?;
+
+            left = Parser::binop(kind, left, right);
+
+            pos = end;
+        }
+
+        Ok((left, pos))
+    }
+
+    // The operator a token represents and its precedence, higher binds tighter
+    fn binop_kind(kind: &TokenKind) -> Option<(BinopKind, u8)> {
+        match kind {
+            TokenKind::Or => Some((BinopKind::Or, 1)),
+            TokenKind::And => Some((BinopKind::And, 2)),
+            TokenKind::DoubleEqual => Some((BinopKind::Equal, 3)),
+            TokenKind::NotEqual => Some((BinopKind::NotEqual, 3)),
+            TokenKind::LessThan => Some((BinopKind::LessThan, 4)),
+            TokenKind::LessThanOrEqual => Some((BinopKind::LessThanOrEqual, 4)),
+            TokenKind::GreaterThan => Some((BinopKind::GreaterThan, 4)),
+            TokenKind::GreaterThanOrEqual => Some((BinopKind::GreaterThanOrEqual, 4)),
+            TokenKind::Plus => Some((BinopKind::Plus, 5)),
+            TokenKind::Minus => Some((BinopKind::Minus, 5)),
+            TokenKind::Times => Some((BinopKind::Times, 6)),
+            TokenKind::Divide => Some((BinopKind::Divide, 6)),
+            TokenKind::Modulo => Some((BinopKind::Modulo, 6)),
+            _ => None,
+        }
+    }
+
+    fn parse_unary(&self, tokens: &[Token], pos: usize) -> Result<(Expr, usize)> {
+        let kind = match self.kind(tokens, pos) {
+            Some(TokenKind::Minus) => UnaryKind::Negate,
+            Some(TokenKind::Not) => UnaryKind::Not,
+            _ => return self.parse_call(tokens, pos),
+        };
+
+        let (expr, end) = self.parse_unary(tokens, pos + 1)?;
+
+        let span = tokens[pos].span.to(expr.span);
+
+        Ok((
+            Expr {
+                kind: ExprKind::Unary(kind, Box::new(expr)),
+                span,
+            },
+            end,
+        ))
+    }
+
+    fn parse_call(&self, tokens: &[Token], pos: usize) -> Result<(Expr, usize)> {
+        let (mut expr, mut pos) = self.parse_literal(tokens, pos)?;
+
+        // Calls and indexes can be chained, like f(1)(2) or s[1][0]
+        loop {
+            let start = expr.span;
+
+            let (kind, end) = match self.kind(tokens, pos) {
+                Some(TokenKind::LBracket) => {
+                    let (args, end) =
+                        self.parse_list(tokens, pos + 1, TokenKind::RBracket, |tokens, pos| {
+                            self.parse_expr(tokens, pos)
+                        })?;
+
+                    (ExprKind::Call(Box::new(expr), args), end)
+                }
+                Some(TokenKind::LSquare) => self.parse_index(expr, tokens, pos + 1)?,
+                _ => break,
+            };
+
+            expr = Expr {
+                kind,
+                span: start.to(tokens[end - 1].span),
+            };
+
+            pos = end;
+        }
+
+        Ok((expr, pos))
+    }
+
+    // Parses what follows the [ after the target, either an index like [1] or a slice like [1:2],
+    // where either bound can be left out
+    fn parse_index(&self, target: Expr, tokens: &[Token], pos: usize) -> Result<(ExprKind, usize)> {
+        let (start, pos) = match self.kind(tokens, pos) {
+            Some(TokenKind::Colon) => (None, pos),
+            _ => {
+                let (start, end) = self.parse_expr(tokens, pos)?;
+
+                (Some(Box::new(start)), end)
+            }
+        };
+
+        match (start, self.kind(tokens, pos)) {
+            (Some(index), Some(TokenKind::RSquare)) => {
+                Ok((ExprKind::Index(Box::new(target), index), pos + 1))
+            }
+            (start, Some(TokenKind::Colon)) => {
+                let (end, pos) = match self.kind(tokens, pos + 1) {
+                    Some(TokenKind::RSquare) => (None, pos + 1),
+                    _ => {
+                        let (end, pos) = self.parse_expr(tokens, pos + 1)?;
+
+                        (Some(Box::new(end)), pos)
+                    }
+                };
+
+                match self.kind(tokens, pos) {
+                    Some(TokenKind::RSquare) => {
+                        Ok((ExprKind::Slice(Box::new(target), start, end), pos + 1))
+                    }
+                    _ => Err(self.unexpected(tokens, pos)),
+                }
+            }
+            _ => Err(self.unexpected(tokens, pos)),
+        }
+    }
+
+    /*
+     * Parses comma separated items up to the closing token, returning the position after it. The
+     * lexer keeps newlines inside braces, so they're skipped around the items of a map.
+     */
+    fn parse_list<T, F>(
+        &self,
+        tokens: &[Token],
+        pos: usize,
+        close: TokenKind,
+        parse_item: F,
+    ) -> Result<(Vec<T>, usize)>
+    where
+        F: Fn(&[Token], usize) -> Result<(T, usize)>,
+    {
+        let mut items = vec![];
+
+        let mut pos = self.skip_newlines(tokens, pos);
+
+        while self.kind(tokens, pos) != Some(&close) {
+            let (item, end) = parse_item(tokens, pos)?;
+
+            items.push(item);
+
+            let end = self.skip_newlines(tokens, end);
+
+            pos = match self.kind(tokens, end) {
+                Some(TokenKind::Comma) => self.skip_newlines(tokens, end + 1),
+                Some(kind) if *kind == close => end,
+                _ => return Err(self.unexpected(tokens, end)),
+            };
+        }
+
+        Ok((items, pos + 1))
+    }
+
+    fn parse_literal(&self, tokens: &[Token], pos: usize) -> Result<(Expr, usize)> {
+        match self.kind(tokens, pos) {
+            Some(TokenKind::LBracket) => {
+                self.parse_expr(tokens, pos + 1).and_then(|(expr, end)| {
+                    match self.kind(tokens, end) {
+                        // The brackets are included in the span of the expression
+                        Some(TokenKind::RBracket) => Ok((
+                            Expr {
+                                span: tokens[pos].span.to(tokens[end].span),
+                                ..expr
+                            },
+                            end + 1,
+                        )),
+                        _ => Err(self.unexpected(tokens, end)),
+                    }
+                })
+            }
+            Some(TokenKind::LSquare) => {
+                let (items, end) =
+                    self.parse_list(tokens, pos + 1, TokenKind::RSquare, |tokens, pos| {
+                        self.parse_expr(tokens, pos)
+                    })?;
 
                 Ok((
-                    Expr::Binop(BinopKind::Times, Box::new(left), Box::new(right)),
-                    pos,
+                    Expr {
+                        kind: ExprKind::List(items),
+                        span: tokens[pos].span.to(tokens[end - 1].span),
+                    },
+                    end,
                 ))
             }
-            Some(Token::Divide) => {
-                let (right, pos) = self.parse_literal(tokens, pos + 1)?;
+            Some(TokenKind::LBrace) => {
+                let (entries, end) =
+                    self.parse_list(tokens, pos + 1, TokenKind::RBrace, |tokens, pos| {
+                        self.parse_entry(tokens, pos)
+                    })?;
 
                 Ok((
-                    Expr::Binop(BinopKind::Divide, Box::new(left), Box::new(right)),
-                    pos,
+                    Expr {
+                        kind: ExprKind::Map(entries),
+                        span: tokens[pos].span.to(tokens[end - 1].span),
+                    },
+                    end,
+                ))
+            }
+            Some(TokenKind::Fn) => self.parse_anonymous_function(tokens, pos),
+            Some(kind) => {
+                let kind = match kind {
+                    TokenKind::Number(num) => ExprKind::Number(*num),
+                    TokenKind::BigNumber(num) => ExprKind::BigNumber(num.clone()),
+                    TokenKind::Float(num) => ExprKind::Float(*num),
+                    TokenKind::True => ExprKind::Bool(true),
+                    TokenKind::False => ExprKind::Bool(false),
+                    TokenKind::Nil => ExprKind::Nil,
+                    TokenKind::String(string) => ExprKind::String(string.to_string()),
+                    TokenKind::Ident(name) => ExprKind::Ident(name.to_string()),
+                    _ => return Err(self.unexpected(tokens, pos)),
+                };
+
+                Ok((
+                    Expr {
+                        kind,
+                        span: tokens[pos].span,
+                    },
+                    pos + 1,
                 ))
             }
-            _ => Ok((left, pos)),
+            None => Err(self.unexpected(tokens, pos)),
         }
     }
 
-    fn parse_literal(&self, tokens: &[Token], pos: usize) -> Result<(Expr, usize)> {
+    // A key and its value in a map literal, like "k": v
+    fn parse_entry(&self, tokens: &[Token], pos: usize) -> Result<((Expr, Expr), usize)> {
+        let (key, end) = self.parse_expr(tokens, pos)?;
+
+        if self.kind(tokens, end) != Some(&TokenKind::Colon) {
+            return Err(self.unexpected(tokens, end));
+        }
+
+        let (val, end) = self.parse_expr(tokens, end + 1)?;
+
+        Ok(((key, val), end))
+    }
+
+    fn binop(kind: BinopKind, left: Expr, right: Expr) -> Expr {
+        let span = left.span.to(right.span);
+
+        Expr {
+            kind: ExprKind::Binop(kind, Box::new(left), Box::new(right)),
+            span,
+        }
+    }
+
+    fn kind<'a>(&self, tokens: &'a [Token], pos: usize) -> Option<&'a TokenKind> {
+        tokens.get(pos).map(|token| &token.kind)
+    }
+
+    // The error for finding the token at pos, or for finding nothing at all
+    fn unexpected(&self, tokens: &[Token], pos: usize) -> ErrorKind {
         match tokens.get(pos) {
-            Some(Token::LBracket) => {
-                self.parse_expr(tokens, pos + 1)
-                    .and_then(|(expr, pos)| match tokens.get(pos) {
-                        Some(Token::RBracket) => Ok((expr, pos + 1)),
-                        Some(token) => Err(ErrorKind::UnexpectedToken(token.clone())),
-                        None => Err(ErrorKind::UnexpectedEndOfInput(pos)),
-                    })
-            }
-            Some(Token::Number(num)) => Ok((Expr::Number(*num), pos + 1)),
-            Some(token) => Err(ErrorKind::UnexpectedToken(token.clone())),
-            None => Err(ErrorKind::UnexpectedEndOfInput(pos)),
+            Some(Token {
+                kind: TokenKind::Newline,
+                span,
+                ..
+            }) => ErrorKind::UnexpectedEndOfLine(*span),
+            Some(token) => ErrorKind::UnexpectedToken(token.kind.to_string(), token.span),
+            None => ErrorKind::UnexpectedEndOfInput(self.end_span()),
+        }
+    }
+
+    // An empty span just after the last token
+    fn end_span(&self) -> Span {
+        match self.tokens.last() {
+            Some(token) => Span::new(
+                token.span.end,
+                token.span.end,
+                token.span.line,
+                token.end_col,
+            ),
+            None => Span::new(0, 0, 1, 1),
+        }
+    }
+}
+
+impl ErrorKind {
+    // The token which was out of place, which for a newline is the end of its line. Running out
+    // of tokens points just past the last one.
+    pub fn span(&self) -> Span {
+        match self {
+            ErrorKind::UnexpectedToken(_, span)
+            | ErrorKind::UnexpectedEndOfLine(span)
+            | ErrorKind::UnexpectedEndOfInput(span) => *span,
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::UnexpectedToken(token, _) => write!(f, "unexpected token `{}`", token),
+            ErrorKind::UnexpectedEndOfLine(_) => write!(f, "unexpected end of line"),
+            ErrorKind::UnexpectedEndOfInput(_) => write!(f, "unexpected end of input"),
+        }
+    }
+}
+
+impl std::error::Error for ErrorKind {}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        ast::{Expr, ExprKind, Stmt, StmtKind},
+        lexer::Lexer,
+        span::Span,
+    };
+
+    use super::{ErrorKind, Parser, Result};
+
+    // Parses the input, returning the first error if there were any
+    fn parse(input: &str) -> Result<Vec<Stmt>> {
+        Parser::new(Lexer::new(input).run().unwrap())
+            .parse()
+            .map_err(|mut errs| errs.remove(0))
+    }
+
+    #[test]
+    fn parsing_spans_works() -> Result {
+        let stmts = parse("let x = (1 + 2) * 3")?;
+
+        assert_eq!(stmts[0].span, Span::new(0, 19, 1, 1));
+
+        match &stmts[0].kind {
+            StmtKind::Binding(_, expr) => assert_eq!(expr.span, Span::new(8, 19, 1, 9)),
+            kind => panic!("expected a binding, got {:?}", kind),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn parsing_multiple_lines_works() -> Result {
+        let input = "\nlet x = 1\n\nlet y = (2\n+ 3); print 4;\n";
+
+        let stmts = parse(input)?;
+
+        assert_eq!(stmts.len(), 3);
+
+        assert_eq!(stmts[1].span, Span::new(12, 27, 4, 1));
+
+        Ok(())
+    }
+
+    // Renders an expression with brackets around every operation
+    fn bracketed(expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Number(num) => num.to_string(),
+            ExprKind::BigNumber(num) => num.to_string(),
+            ExprKind::Float(num) => format!("{:?}", num),
+            ExprKind::Bool(val) => val.to_string(),
+            ExprKind::Nil => "nil".to_string(),
+            ExprKind::String(string) => format!("{:?}", string),
+            ExprKind::List(items) => format!(
+                "[{}]",
+                items.iter().map(bracketed).collect::<Vec<_>>().join(", ")
+            ),
+            ExprKind::Map(entries) => format!(
+                "{{{}}}",
+                entries
+                    .iter()
+                    .map(|(key, val)| format!("{}: {}", bracketed(key), bracketed(val)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            ExprKind::Ident(name) => name.to_string(),
+            ExprKind::Binop(kind, left, right) => {
+                format!("({} {:?} {})", bracketed(left), kind, bracketed(right))
+            }
+            ExprKind::Unary(kind, expr) => format!("({:?} {})", kind, bracketed(expr)),
+            ExprKind::Call(callee, args) => format!(
+                "{}({})",
+                bracketed(callee),
+                args.iter().map(bracketed).collect::<Vec<_>>().join(", ")
+            ),
+            ExprKind::Function(params, _) => format!("fn({})", params.join(", ")),
+            ExprKind::Index(target, index) => {
+                format!("{}[{}]", bracketed(target), bracketed(index))
+            }
+            ExprKind::Slice(target, start, end) => format!(
+                "{}[{}:{}]",
+                bracketed(target),
+                start.as_deref().map(bracketed).unwrap_or_default(),
+                end.as_deref().map(bracketed).unwrap_or_default()
+            ),
+        }
+    }
+
+    fn parse_bracketed(input: &str) -> Result<String> {
+        match &parse(input)?[0].kind {
+            StmtKind::Expr(expr) => Ok(bracketed(expr)),
+            kind => panic!("expected an expression, got {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn parsing_operator_chains_works() -> Result {
+        assert_eq!(parse_bracketed("1 + 2 + 3")?, "((1 Plus 2) Plus 3)");
+
+        assert_eq!(parse_bracketed("2 * 3 * 4")?, "((2 Times 3) Times 4)");
+
+        assert_eq!(parse_bracketed("8 - 4 - 2")?, "((8 Minus 4) Minus 2)");
+
+        Ok(())
+    }
+
+    #[test]
+    fn parsing_precedence_works() -> Result {
+        assert_eq!(
+            parse_bracketed("1 + 2 * 3 % 4 - 5")?,
+            "((1 Plus ((2 Times 3) Modulo 4)) Minus 5)"
+        );
+
+        assert_eq!(
+            parse_bracketed("1 < 2 == 3 >= 4 || 5 != 6 && 7 <= 8")?,
+            "(((1 LessThan 2) Equal (3 GreaterThanOrEqual 4)) Or ((5 NotEqual 6) And (7 LessThanOrEqual 8)))"
+        );
+
+        assert_eq!(
+            parse_bracketed("-1 * -(2 + 3)")?,
+            "((Negate 1) Times (Negate (2 Plus 3)))"
+        );
+
+        assert_eq!(
+            parse_bracketed("!!(1 > 2)")?,
+            "(Not (Not (1 GreaterThan 2)))"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn parsing_literals_works() -> Result {
+        assert_eq!(
+            parse_bracketed(r#"x == true || "a\n" != y && !false"#)?,
+            r#"((x Equal true) Or (("a\n" NotEqual y) And (Not false)))"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn parsing_calls_works() -> Result {
+        assert_eq!(parse_bracketed("f()")?, "f()");
+
+        assert_eq!(
+            parse_bracketed("-f(1, g(2) * 3,)(x) + 1")?,
+            "((Negate f(1, (g(2) Times 3))(x)) Plus 1)"
+        );
+
+        assert!(parse("f(1 2)").is_err());
+
+        assert!(parse("f(,)").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn parsing_indexes_works() -> Result {
+        assert_eq!(parse_bracketed("s[1 + 2]")?, "s[(1 Plus 2)]");
+
+        assert_eq!(parse_bracketed("f(x)[0][1:2]")?, "f(x)[0][1:2]");
+
+        assert_eq!(parse_bracketed("-s[:n]")?, "(Negate s[:n])");
+
+        assert_eq!(parse_bracketed("s[1:]")?, "s[1:]");
+
+        assert_eq!(parse_bracketed("s[:]")?, "s[:]");
+
+        assert!(parse("s[]").is_err());
+
+        assert!(parse("s[1:2:3]").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn parsing_lists_works() -> Result {
+        assert_eq!(parse_bracketed("[]")?, "[]");
+
+        assert_eq!(parse_bracketed("[1, [2, 3], f(x)]")?, "[1, [2, 3], f(x)]");
+
+        assert_eq!(parse_bracketed("[1 + 2][0]")?, "[(1 Plus 2)][0]");
+
+        // The brackets keep newlines inside them from ending the statement
+        assert_eq!(parse_bracketed("[\n  1,\n  2\n]")?, "[1, 2]");
+
+        match &parse("xs[i + 1] = [x]")?[0].kind {
+            StmtKind::IndexAssign(list, index, expr) => {
+                assert_eq!(bracketed(list), "xs");
+
+                assert_eq!(bracketed(index), "(i Plus 1)");
+
+                assert_eq!(bracketed(expr), "[x]");
+            }
+            kind => panic!("expected an index assignment, got {:?}", kind),
+        }
+
+        assert!(parse("[1, 2").is_err());
+
+        assert!(parse("f(x) = 1").is_err());
+
+        assert!(parse("xs[1:] = 1").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn parsing_maps_works() -> Result {
+        assert_eq!(parse_bracketed("{}")?, "{}");
+
+        assert_eq!(
+            parse_bracketed("{\"a\": 1 + 2, 3: {\"b\": [x]}}")?,
+            "{\"a\": (1 Plus 2), 3: {\"b\": [x]}}"
+        );
+
+        assert_eq!(parse_bracketed("{\"a\": 1}[\"a\"]")?, "{\"a\": 1}[\"a\"]");
+
+        // Newlines are kept inside braces, but a map can still span lines
+        assert_eq!(
+            parse_bracketed("{\n  \"a\": 1,\n  \"b\": 2,\n}")?,
+            "{\"a\": 1, \"b\": 2}"
+        );
+
+        assert!(parse("{\"a\" 1}").is_err());
+
+        assert!(parse("{\"a\": 1").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn parsing_functions_works() -> Result {
+        let stmts = parse("fn add(x, y) {\n  return x + y\n}\nfn none() {}")?;
+
+        match &stmts[0].kind {
+            StmtKind::Function(name, params, body) => {
+                assert_eq!(name, "add");
+
+                assert_eq!(params, &vec!["x".to_string(), "y".to_string()]);
+
+                assert!(matches!(body[0].kind, StmtKind::Return(Some(_))));
+            }
+            kind => panic!("expected a function, got {:?}", kind),
+        }
+
+        assert!(
+            matches!(&stmts[1].kind, StmtKind::Function(_, params, body) if params.is_empty() && body.is_empty())
+        );
+
+        assert!(parse("fn f(1) {}").is_err());
+
+        // A bare return can end the line, the statement or the block
+        for input in &[
+            "fn f() {\n  return\n}",
+            "fn f() { return; }",
+            "fn f() { return }",
+        ] {
+            match &parse(input)?[0].kind {
+                StmtKind::Function(_, _, body) => {
+                    assert!(matches!(body[0].kind, StmtKind::Return(None)))
+                }
+                kind => panic!("expected a function, got {:?}", kind),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn parsing_anonymous_functions_works() -> Result {
+        let stmts = parse("apply(fn(x) {\n  let y = x\n  return y\n}, 1)\nfn() {}")?;
+
+        match &stmts[0].kind {
+            StmtKind::Expr(Expr {
+                kind: ExprKind::Call(_, args),
+                ..
+            }) => {
+                assert!(
+                    matches!(&args[0].kind, ExprKind::Function(params, body) if params.len() == 1 && body.len() == 2)
+                );
+
+                assert_eq!(args[0].span, Span::new(6, 38, 1, 7));
+            }
+            kind => panic!("expected a call, got {:?}", kind),
+        }
+
+        assert!(matches!(
+            &stmts[1].kind,
+            StmtKind::Expr(Expr {
+                kind: ExprKind::Function(..),
+                ..
+            })
+        ));
+
+        assert_eq!(parse_bracketed("fn(x, y) {}(1, 2)")?, "fn(x, y)(1, 2)");
+
+        Ok(())
+    }
+
+    #[test]
+    fn parsing_reports_incomplete_statements() {
+        assert!(matches!(
+            parse("let x = 1 +"),
+            Err(ErrorKind::UnexpectedEndOfInput(_))
+        ));
+    }
+
+    #[test]
+    fn parsing_reports_trailing_tokens() {
+        match parse("let x = 1\nlet y = 2 3") {
+            Err(ErrorKind::UnexpectedToken(token, span)) => {
+                assert_eq!(token, "3");
+
+                assert_eq!(span, Span::new(20, 21, 2, 11));
+            }
+            result => panic!("expected an unexpected token, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn parsing_reports_misplaced_tokens_in_bindings() {
+        match parse("let = 3") {
+            Err(ErrorKind::UnexpectedToken(token, _)) => assert_eq!(token, "="),
+            result => panic!("expected an unexpected token, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn parsing_recovers_after_errors() {
+        let input = "let x = )\nprint 1\nlet = 2; print 3 3\nlet y = 1 +";
+
+        let errs = Parser::new(Lexer::new(input).run().unwrap())
+            .parse()
+            .unwrap_err();
+
+        let lines = errs.iter().map(|err| err.span().line).collect::<Vec<_>>();
+
+        assert_eq!(lines, vec![1, 3, 3, 4]);
+    }
+
+    #[test]
+    fn parsing_control_flow_works() -> Result {
+        let input = "while i < 3 {\n  if i == 1 { x = 2 } else if i == 2 {\n    x = 3\n  }\n  else { x = 4; i = i + 1 }\n}";
+
+        let stmts = parse(input)?;
+
+        assert_eq!(stmts.len(), 1);
+
+        assert_eq!(stmts[0].span, Span::new(0, input.len(), 1, 1));
+
+        match &stmts[0].kind {
+            StmtKind::While(_, body) => match &body[0].kind {
+                StmtKind::If(_, then_block, Some(else_block)) => {
+                    assert_eq!(then_block.len(), 1);
+
+                    assert!(matches!(else_block[0].kind, StmtKind::If(_, _, Some(_))));
+                }
+                kind => panic!("expected an if, got {:?}", kind),
+            },
+            kind => panic!("expected a while, got {:?}", kind),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn parsing_recovers_inside_blocks() {
+        let input = "while true {\n  let = 1\n  x = 2\n}\nif { print 1 }\nprint 2 3";
+
+        let errs = Parser::new(Lexer::new(input).run().unwrap())
+            .parse()
+            .unwrap_err();
+
+        let lines = errs.iter().map(|err| err.span().line).collect::<Vec<_>>();
+
+        assert_eq!(lines, vec![2, 5, 6]);
+    }
+
+    #[test]
+    fn parsing_reports_unclosed_blocks() {
+        assert!(matches!(
+            parse("while true {\n  print 1\n"),
+            Err(ErrorKind::UnexpectedEndOfInput(_))
+        ));
+
+        assert!(matches!(
+            parse("print 1 }"),
+            Err(ErrorKind::UnexpectedToken(token, _)) if token == "}"
+        ));
+    }
+
+    #[test]
+    fn unexpected_end_of_input_points_past_last_token() {
+        let parser = Parser::new(Lexer::new("(1 + 2").run().unwrap());
+
+        match parser.parse_stmt(&parser.tokens, 0) {
+            Err(ErrorKind::UnexpectedEndOfInput(span)) => {
+                assert_eq!(span, Span::new(6, 6, 1, 7))
+            }
+            result => panic!("expected end of input, got {:?}", result),
+        }
+
+        // Columns count chars, not bytes
+        match parse("let x = (\"héllo\"") {
+            Err(ErrorKind::UnexpectedEndOfInput(span)) => {
+                assert_eq!((span.line, span.col), (1, 17))
+            }
+            result => panic!("expected end of input, got {:?}", result),
         }
     }
 }
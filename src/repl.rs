@@ -0,0 +1,242 @@
+use std::io::{self, BufRead, Write};
+
+use crate::{
+    engine::Engine,
+    lexer::{Lexer, Token, TokenKind},
+    vm::{stack::Limits, value::Value},
+    ErrorKind, Result,
+};
+
+const PROMPT: &str = ">> ";
+
+const CONTINUATION_PROMPT: &str = ".. ";
+
+pub struct Repl {
+    engine: Engine,
+    history: Vec<String>,
+}
+
+impl Repl {
+    pub fn new(limits: Limits) -> Result<Self> {
+        Ok(Self {
+            engine: Engine::with_limits(limits)?,
+            history: vec![],
+        })
+    }
+
+    pub fn run(&mut self) -> Result {
+        let stdin = io::stdin();
+
+        let mut input = String::new();
+
+        loop {
+            if input.is_empty() {
+                print!("{}", PROMPT);
+            } else {
+                print!("{}", CONTINUATION_PROMPT);
+            }
+
+            io::stdout().flush().map_err(ErrorKind::IoError)?;
+
+            let mut line = String::new();
+
+            // Nothing was read, so stdin has been closed
+            if stdin
+                .lock()
+                .read_line(&mut line)
+                .map_err(ErrorKind::IoError)?
+                == 0
+            {
+                println!();
+
+                return Ok(());
+            }
+
+            let line = line.trim_end();
+
+            if input.is_empty() {
+                match line {
+                    "" => continue,
+                    ":quit" => return Ok(()),
+                    ":history" => {
+                        self.history
+                            .iter()
+                            .enumerate()
+                            .for_each(|(num, entry)| println!("{:>4}  {}", num + 1, entry));
+
+                        continue;
+                    }
+                    _ => {}
+                }
+            } else {
+                input.push('\n');
+            }
+
+            input.push_str(line);
+
+            match self.eval(&input) {
+                Ok(false) => continue,
+                Ok(true) => {}
+                Err(err) => eprintln!("{}", err.render(&input)),
+            }
+
+            self.history.push(std::mem::take(&mut input));
+        }
+    }
+
+    // Returns false when the input is incomplete and more lines are needed. The result of the
+    // input is printed, unless it's nil.
+    fn eval(&mut self, input: &str) -> Result<bool> {
+        let tokens = Lexer::new(input).run().map_err(ErrorKind::LexerError)?;
+
+        if Repl::is_incomplete(&tokens) {
+            return Ok(false);
+        }
+
+        match self.engine.eval(input)? {
+            Value::Nil => {}
+            val => println!("{}", val),
+        }
+
+        Ok(true)
+    }
+
+    fn is_incomplete(tokens: &[Token]) -> bool {
+        let depth = tokens.iter().fold(0, |depth, token| match token.kind {
+            TokenKind::LBracket | TokenKind::LBrace | TokenKind::LSquare => depth + 1,
+            TokenKind::RBracket | TokenKind::RBrace | TokenKind::RSquare => depth - 1,
+            _ => depth,
+        });
+
+        depth > 0 || Repl::awaits_else(tokens)
+    }
+
+    // An if whose block has just closed may still have an else on the next line, so it waits for
+    // that line. A blank line runs it as it is. Ifs which already end in a plain else are done.
+    fn awaits_else(tokens: &[Token]) -> bool {
+        if !matches!(tokens.last(), Some(token) if token.kind == TokenKind::RBrace) {
+            return false;
+        }
+
+        // Where the last statement starts. A newline followed by an else doesn't end the if.
+        let mut depth = 0;
+        let mut start = 0;
+
+        for (pos, token) in tokens.iter().enumerate() {
+            let before_else =
+                matches!(tokens.get(pos + 1), Some(next) if next.kind == TokenKind::Else);
+
+            match token.kind {
+                TokenKind::LBracket | TokenKind::LBrace | TokenKind::LSquare => depth += 1,
+                TokenKind::RBracket | TokenKind::RBrace | TokenKind::RSquare => depth -= 1,
+                TokenKind::Newline | TokenKind::Semicolon if depth == 0 && !before_else => {
+                    start = pos + 1
+                }
+                _ => {}
+            }
+        }
+
+        if !matches!(tokens.get(start), Some(token) if token.kind == TokenKind::If) {
+            return false;
+        }
+
+        // The brace which opens the last block, and whether that block is a plain else
+        let mut depth = 0;
+
+        let open = tokens.iter().rposition(|token| {
+            match token.kind {
+                TokenKind::RBrace => depth += 1,
+                TokenKind::LBrace => depth -= 1,
+                _ => {}
+            }
+
+            depth == 0
+        });
+
+        !matches!(open, Some(pos) if pos > 0 && tokens[pos - 1].kind == TokenKind::Else)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        lexer::Lexer,
+        vm::{stack::Limits, value::Value},
+        Result,
+    };
+
+    use super::Repl;
+
+    fn global(repl: &Repl, name: &str) -> Option<Value> {
+        repl.engine.get(name)
+    }
+
+    #[test]
+    fn globals_persist_between_inputs() -> Result {
+        let mut repl = Repl::new(Limits::default())?;
+
+        assert!(repl.eval("let x = 400")?);
+
+        assert!(repl.eval("let y = 100")?);
+
+        assert_eq!(global(&repl, "x"), Some(Value::Int(400)));
+
+        assert_eq!(global(&repl, "y"), Some(Value::Int(100)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn loops_run_after_earlier_inputs() -> Result {
+        let mut repl = Repl::new(Limits::default())?;
+
+        assert!(repl.eval("let x = 0")?);
+
+        assert!(!repl.eval("while x < 3 {")?);
+
+        assert!(repl.eval("while x < 3 {\n  x = x + 1\n}")?);
+
+        assert_eq!(global(&repl, "x"), Some(Value::Int(3)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn open_brackets_are_incomplete() -> Result {
+        let mut repl = Repl::new(Limits::default())?;
+
+        assert!(!repl.eval("let x = (1 + ")?);
+
+        assert!(repl.eval("let x = (1 + 2)")?);
+
+        assert!(Repl::is_incomplete(&Lexer::new("((1)").run().unwrap()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn ifs_wait_for_an_else_on_the_next_line() -> Result {
+        let mut repl = Repl::new(Limits::default())?;
+
+        assert!(repl.eval("let x = 0")?);
+
+        assert!(!repl.eval("if x > 0 {\n  x = 1\n}")?);
+
+        assert!(!repl.eval("if x > 0 {\n  x = 1\n}\nelse if x < 0 {\n  x = 2\n}")?);
+
+        assert!(
+            repl.eval("if x > 0 {\n  x = 1\n}\nelse if x < 0 {\n  x = 2\n}\nelse {\n  x = 3\n}")?
+        );
+
+        assert_eq!(global(&repl, "x"), Some(Value::Int(3)));
+
+        // A blank line runs the if without an else
+        assert!(!repl.eval("if true { x = 4 }")?);
+
+        assert!(repl.eval("if true { x = 4 }\n")?);
+
+        assert_eq!(global(&repl, "x"), Some(Value::Int(4)));
+
+        Ok(())
+    }
+}
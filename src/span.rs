@@ -0,0 +1,130 @@
+use std::{fmt, ops::Range};
+
+// A region of the source, as a byte range along with the 1-based line and column of its start
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Span {
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    pub(crate) line: usize,
+    pub(crate) col: usize,
+}
+
+impl Span {
+    pub(crate) fn new(start: usize, end: usize, line: usize, col: usize) -> Self {
+        Span {
+            start,
+            end,
+            line,
+            col,
+        }
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn col(&self) -> usize {
+        self.col
+    }
+
+    // The bytes of the source the span covers
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    // Spans from the start of this span to the end of the other
+    pub(crate) fn to(self, other: Span) -> Self {
+        Span {
+            end: other.end,
+            ..self
+        }
+    }
+
+    /*
+     * Renders a message along with the line the span starts on, underlining the span:
+     *
+     * error: unexpected character '$'
+     *  --> 1:9
+     *   |
+     * 1 | let x = $
+     *   |         ^
+     */
+    pub(crate) fn render(&self, source: &str, message: &str) -> String {
+        let line = source.lines().nth(self.line - 1).unwrap_or("");
+
+        let line_num = self.line.to_string();
+
+        let gutter = " ".repeat(line_num.len());
+
+        let line_start = source
+            .lines()
+            .take(self.line - 1)
+            .map(|line| line.len() + 1)
+            .sum::<usize>();
+
+        // The underline stops at the end of the line for spans covering several lines
+        let len = source
+            .get(self.start..self.end.min(line_start + line.len()))
+            .map(|text| text.chars().count())
+            .unwrap_or(0)
+            .max(1);
+
+        format!(
+            "error: {}\n{}--> {}\n{} |\n{} | {}\n{} | {}{}",
+            message,
+            gutter,
+            self,
+            gutter,
+            line_num,
+            line,
+            gutter,
+            " ".repeat(self.col - 1),
+            "^".repeat(len)
+        )
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Span;
+
+    #[test]
+    fn rendering_underlines_span() {
+        let span = Span::new(8, 11, 1, 9);
+
+        assert_eq!(
+            span.render("let x = 1 +", "unexpected token"),
+            "error: unexpected token\n --> 1:9\n  |\n1 | let x = 1 +\n  |         ^^^"
+        );
+    }
+
+    #[test]
+    fn rendering_picks_span_line() {
+        let span = Span::new(14, 15, 2, 5);
+
+        assert_eq!(
+            span.render("let x = 1\nlet $ = 2", "unexpected character '$'"),
+            "error: unexpected character '$'\n --> 2:5\n  |\n2 | let $ = 2\n  |     ^"
+        );
+    }
+
+    #[test]
+    fn to_covers_both_spans() {
+        let span = Span::new(0, 3, 1, 1).to(Span::new(8, 9, 1, 9));
+
+        assert_eq!(span, Span::new(0, 9, 1, 1));
+    }
+
+    #[test]
+    fn positions_are_public() {
+        let span = Span::new(14, 15, 2, 5);
+
+        assert_eq!((span.line(), span.col(), span.range()), (2, 5, 14..15));
+    }
+}
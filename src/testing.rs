@@ -0,0 +1,22 @@
+// Helpers for tests which run whole scripts, shared by the modules whose behaviour they check
+
+use crate::{vm::ErrorKind as VmErrorKind, Engine, ErrorKind, Value};
+
+// Runs the source in a new engine, giving the value of its last line
+pub(crate) fn eval(source: &str) -> Value {
+    Engine::new()
+        .and_then(|mut engine| engine.eval(source))
+        .expect("evaluating failed")
+}
+
+// Runs the source in a new engine, which must fail while running
+pub(crate) fn eval_err(source: &str) -> VmErrorKind {
+    match Engine::new().and_then(|mut engine| engine.eval(source)) {
+        Err(ErrorKind::VmError(err, _)) => err,
+        result => panic!("expected a runtime error, got {:?}", result),
+    }
+}
+
+pub(crate) fn string(s: &str) -> Value {
+    Value::String(s.to_string())
+}
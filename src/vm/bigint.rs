@@ -0,0 +1,465 @@
+use std::{
+    cmp::Ordering,
+    convert::TryFrom,
+    fmt,
+    ops::{Add, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+// Each limb holds nine decimal digits, which makes parsing and printing simple
+const BASE: u64 = 1_000_000_000;
+
+const BASE_DIGITS: usize = 9;
+
+// An integer of any size. Ints which overflow are promoted to these, and results which fit back
+// into an int are demoted again by the evaluator.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BigInt {
+    negative: bool,
+
+    // Least significant first, without any trailing zero limbs. Zero has no limbs.
+    limbs: Vec<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseBigIntError;
+
+impl BigInt {
+    fn new(negative: bool, mut limbs: Vec<u32>) -> Self {
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+
+        // There's no negative zero
+        let negative = negative && !limbs.is_empty();
+
+        BigInt { negative, limbs }
+    }
+
+    pub(crate) fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub(crate) fn to_i32(&self) -> Option<i32> {
+        // Three limbs can't fit into an int, but they do fit into an i64
+        if self.limbs.len() > 2 {
+            return None;
+        }
+
+        let magnitude = self
+            .limbs
+            .iter()
+            .rev()
+            .fold(0i64, |acc, limb| acc * BASE as i64 + i64::from(*limb));
+
+        let val = if self.negative { -magnitude } else { magnitude };
+
+        i32::try_from(val).ok()
+    }
+
+    // Going through the digits rounds correctly, which adding up the limbs as floats wouldn't
+    pub(crate) fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    // Compares exactly, where converting either side would round. None if the float is NaN.
+    pub(crate) fn cmp_f64(&self, other: f64) -> Option<Ordering> {
+        if other.is_nan() {
+            return None;
+        }
+
+        if other.is_infinite() {
+            return Some(if other > 0.0 {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            });
+        }
+
+        // Formatting a float with a precision prints its exact value
+        let floor = format!("{:.0}", other.floor()).parse::<BigInt>().ok()?;
+
+        // Equal to the floor means less than the float, if the float has a fraction
+        Some(match self.cmp(&floor) {
+            Ordering::Equal if other.fract() != 0.0 => Ordering::Less,
+            ord => ord,
+        })
+    }
+
+    // Truncating division like ints, so the remainder has the sign of the dividend. None if the
+    // divisor is zero.
+    pub(crate) fn div_rem(&self, other: &BigInt) -> Option<(BigInt, BigInt)> {
+        if other.is_zero() {
+            return None;
+        }
+
+        let (quotient, remainder) = div_rem_magnitude(&self.limbs, &other.limbs);
+
+        Some((
+            BigInt::new(self.negative != other.negative, quotient),
+            BigInt::new(self.negative, remainder),
+        ))
+    }
+}
+
+fn cmp_magnitude(l: &[u32], r: &[u32]) -> Ordering {
+    l.len()
+        .cmp(&r.len())
+        .then_with(|| l.iter().rev().cmp(r.iter().rev()))
+}
+
+fn add_magnitude(l: &[u32], r: &[u32]) -> Vec<u32> {
+    let mut limbs = Vec::with_capacity(l.len().max(r.len()) + 1);
+
+    let mut carry = 0;
+
+    for i in 0..l.len().max(r.len()) {
+        let sum = u64::from(*l.get(i).unwrap_or(&0)) + u64::from(*r.get(i).unwrap_or(&0)) + carry;
+
+        limbs.push((sum % BASE) as u32);
+
+        carry = sum / BASE;
+    }
+
+    limbs.push(carry as u32);
+
+    limbs
+}
+
+// The larger magnitude must come first
+fn sub_magnitude(l: &[u32], r: &[u32]) -> Vec<u32> {
+    let mut limbs = Vec::with_capacity(l.len());
+
+    let mut borrow = 0;
+
+    for (i, limb) in l.iter().enumerate() {
+        let sub = i64::from(*r.get(i).unwrap_or(&0)) + borrow;
+
+        let mut diff = i64::from(*limb) - sub;
+
+        borrow = 0;
+
+        if diff < 0 {
+            diff += BASE as i64;
+
+            borrow = 1;
+        }
+
+        limbs.push(diff as u32);
+    }
+
+    limbs
+}
+
+fn mul_magnitude(l: &[u32], r: &[u32]) -> Vec<u32> {
+    let mut limbs = vec![0u32; l.len() + r.len()];
+
+    for (i, l) in l.iter().enumerate() {
+        let mut carry = 0;
+
+        for (j, r) in r.iter().enumerate() {
+            let product = u64::from(limbs[i + j]) + u64::from(*l) * u64::from(*r) + carry;
+
+            limbs[i + j] = (product % BASE) as u32;
+
+            carry = product / BASE;
+        }
+
+        limbs[i + r.len()] = carry as u32;
+    }
+
+    limbs
+}
+
+// Schoolbook long division, finding each limb of the quotient by binary search
+fn div_rem_magnitude(l: &[u32], r: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    let mut quotient = vec![0u32; l.len()];
+
+    let mut remainder = BigInt::new(false, vec![]);
+
+    let divisor = BigInt::new(false, r.to_vec());
+
+    let multiple = |limb| &divisor * &BigInt::new(false, vec![limb]);
+
+    for (i, limb) in l.iter().enumerate().rev() {
+        remainder.limbs.insert(0, *limb);
+
+        remainder = BigInt::new(false, remainder.limbs);
+
+        let (mut low, mut high) = (0, BASE as u32 - 1);
+
+        while low < high {
+            let mid = low + (high - low).div_ceil(2);
+
+            if multiple(mid) > remainder {
+                high = mid - 1;
+            } else {
+                low = mid;
+            }
+        }
+
+        quotient[i] = low;
+
+        remainder = &remainder - &multiple(low);
+    }
+
+    (quotient, remainder.limbs)
+}
+
+fn from_magnitude(negative: bool, mut magnitude: u64) -> BigInt {
+    let mut limbs = vec![];
+
+    while magnitude > 0 {
+        limbs.push((magnitude % BASE) as u32);
+
+        magnitude /= BASE;
+    }
+
+    BigInt::new(negative, limbs)
+}
+
+impl From<i32> for BigInt {
+    fn from(val: i32) -> Self {
+        from_magnitude(val < 0, i64::from(val).unsigned_abs())
+    }
+}
+
+impl From<usize> for BigInt {
+    fn from(val: usize) -> Self {
+        from_magnitude(false, val as u64)
+    }
+}
+
+impl FromStr for BigInt {
+    type Err = ParseBigIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s),
+        };
+
+        if digits.is_empty() || !digits.bytes().all(|digit| digit.is_ascii_digit()) {
+            return Err(ParseBigIntError);
+        }
+
+        // Limbs are taken from the end of the digits, as the last limb may be shorter
+        let limbs = digits
+            .as_bytes()
+            .rchunks(BASE_DIGITS)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .fold(0, |acc, digit| acc * 10 + u32::from(digit - b'0'))
+            })
+            .collect();
+
+        Ok(BigInt::new(negative, limbs))
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(self.negative, add_magnitude(&self.limbs, &other.limbs));
+        }
+
+        // The signs differ, so the smaller magnitude is taken from the larger
+        match cmp_magnitude(&self.limbs, &other.limbs) {
+            Ordering::Less => BigInt::new(other.negative, sub_magnitude(&other.limbs, &self.limbs)),
+            _ => BigInt::new(self.negative, sub_magnitude(&self.limbs, &other.limbs)),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &-other
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        BigInt::new(
+            self.negative != other.negative,
+            mul_magnitude(&self.limbs, &other.limbs),
+        )
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::new(!self.negative, self.limbs.clone())
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.limbs, &other.limbs),
+            (true, true) => cmp_magnitude(&other.limbs, &self.limbs),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut limbs = self.limbs.iter().rev();
+
+        match limbs.next() {
+            Some(first) => {
+                if self.negative {
+                    write!(f, "-")?;
+                }
+
+                write!(f, "{}", first)?;
+
+                // Every limb but the most significant is padded to its full width
+                limbs.try_for_each(|limb| write!(f, "{:0width$}", limb, width = BASE_DIGITS))
+            }
+            None => write!(f, "0"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cmp::Ordering;
+
+    use super::BigInt;
+
+    fn big(s: &str) -> BigInt {
+        s.parse().expect("parsing failed")
+    }
+
+    #[test]
+    fn parsing_and_display_works() {
+        for s in &[
+            "0",
+            "7",
+            "-7",
+            "1000000000",
+            "-123456789012345678901234567890",
+        ] {
+            assert_eq!(big(s).to_string(), *s);
+        }
+
+        assert_eq!(big("000012").to_string(), "12");
+
+        assert_eq!(big("-0").to_string(), "0");
+
+        assert!("12a".parse::<BigInt>().is_err());
+
+        assert!("".parse::<BigInt>().is_err());
+    }
+
+    #[test]
+    fn arithmetic_works() {
+        let l = big("123456789012345678901234567890");
+
+        let r = big("-987654321098765432109876543210");
+
+        assert_eq!((&l + &r).to_string(), "-864197532086419753208641975320");
+
+        assert_eq!((&l - &r).to_string(), "1111111110111111111011111111100");
+
+        assert_eq!(
+            (&l * &r).to_string(),
+            "-121932631137021795226185032733622923332237463801111263526900"
+        );
+
+        assert_eq!((&l - &l), BigInt::from(0));
+
+        assert_eq!(
+            (&big("1000000000") - &BigInt::from(1)).to_string(),
+            "999999999"
+        );
+    }
+
+    #[test]
+    fn division_truncates() {
+        let (quotient, remainder) = big("-987654321098765432109876543210")
+            .div_rem(&big("123456789012345678901"))
+            .unwrap();
+
+        assert_eq!(quotient.to_string(), "-8000000072");
+
+        assert_eq!(remainder.to_string(), "-111111192012987662338");
+
+        let (quotient, remainder) = BigInt::from(7).div_rem(&BigInt::from(-2)).unwrap();
+
+        assert_eq!((quotient, remainder), (BigInt::from(-3), BigInt::from(1)));
+
+        assert!(BigInt::from(1).div_rem(&BigInt::from(0)).is_none());
+    }
+
+    #[test]
+    fn conversions_work() {
+        assert_eq!(BigInt::from(i32::MIN).to_i32(), Some(i32::MIN));
+
+        assert_eq!(BigInt::from(i32::MAX).to_string(), "2147483647");
+
+        assert_eq!(big("2147483648").to_i32(), None);
+
+        assert_eq!(big("-2147483648").to_i32(), Some(i32::MIN));
+
+        assert_eq!(big("1000000000000000000000").to_f64(), 1e21);
+
+        assert!(big("-5") < big("3"));
+
+        assert!(big("-50000000000") < big("-5"));
+    }
+
+    #[test]
+    fn comparing_with_floats_is_exact() {
+        let big_float = 100000000000000000000.0;
+
+        assert_eq!(
+            big("100000000000000000000").cmp_f64(big_float),
+            Some(Ordering::Equal)
+        );
+
+        assert_eq!(
+            big("99999999999999999999").cmp_f64(big_float),
+            Some(Ordering::Less)
+        );
+
+        assert_eq!(
+            big("100000000000000000001").cmp_f64(big_float),
+            Some(Ordering::Greater)
+        );
+
+        assert_eq!(
+            big("-3000000000").cmp_f64(-2999999999.5),
+            Some(Ordering::Less)
+        );
+
+        assert_eq!(
+            big("3000000000").cmp_f64(2999999999.5),
+            Some(Ordering::Greater)
+        );
+
+        assert_eq!(
+            big("3000000000").cmp_f64(f64::INFINITY),
+            Some(Ordering::Less)
+        );
+
+        assert_eq!(big("3000000000").cmp_f64(f64::NAN), None);
+    }
+}
@@ -0,0 +1,310 @@
+use std::{cell::RefCell, collections::BTreeMap};
+
+use super::{
+    inter::Inter,
+    native::NativeFn,
+    value::{MapKey, Value},
+    ErrorKind, Result,
+};
+
+// A native provided by the interpreter itself, which every interpreter registers when it's made
+pub(crate) struct Builtin {
+    pub(crate) name: &'static str,
+    pub(crate) arity: usize,
+    pub(crate) func: NativeFn,
+}
+
+pub(crate) const BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "len",
+        arity: 1,
+        func: len,
+    },
+    Builtin {
+        name: "trim",
+        arity: 1,
+        func: trim,
+    },
+    Builtin {
+        name: "to_upper",
+        arity: 1,
+        func: to_upper,
+    },
+    Builtin {
+        name: "contains",
+        arity: 2,
+        func: contains,
+    },
+    Builtin {
+        name: "split",
+        arity: 2,
+        func: split,
+    },
+    Builtin {
+        name: "push",
+        arity: 2,
+        func: push,
+    },
+    Builtin {
+        name: "pop",
+        arity: 1,
+        func: pop,
+    },
+    Builtin {
+        name: "keys",
+        arity: 1,
+        func: keys,
+    },
+    Builtin {
+        name: "remove",
+        arity: 2,
+        func: remove,
+    },
+    Builtin {
+        name: "get",
+        arity: 2,
+        func: get,
+    },
+];
+
+fn invalid_argument(name: &str, val: &Value) -> ErrorKind {
+    ErrorKind::InvalidArgument {
+        name: name.to_string(),
+        val: val.clone(),
+    }
+}
+
+fn string<'a>(name: &str, val: &'a Value) -> Result<&'a str> {
+    match val {
+        Value::String(string) => Ok(string),
+        val => Err(invalid_argument(name, val)),
+    }
+}
+
+fn list<'a>(name: &str, val: &'a Value) -> Result<&'a RefCell<Vec<Value>>> {
+    match val {
+        Value::List(items) => Ok(items),
+        val => Err(invalid_argument(name, val)),
+    }
+}
+
+fn map<'a>(name: &str, val: &'a Value) -> Result<&'a RefCell<BTreeMap<MapKey, Value>>> {
+    match val {
+        Value::Map(entries) => Ok(entries),
+        val => Err(invalid_argument(name, val)),
+    }
+}
+
+fn key(name: &str, val: &Value) -> Result<MapKey> {
+    MapKey::new(val).ok_or_else(|| invalid_argument(name, val))
+}
+
+// Strings are measured in chars, the same as they're indexed
+fn len(_: &mut Inter, args: &[Value]) -> Result<Value> {
+    match &args[0] {
+        Value::String(string) => Ok(Value::from(string.chars().count())),
+        Value::List(items) => Ok(Value::from(items.borrow().len())),
+        Value::Map(entries) => Ok(Value::from(entries.borrow().len())),
+        val => Err(invalid_argument("len", val)),
+    }
+}
+
+fn trim(_: &mut Inter, args: &[Value]) -> Result<Value> {
+    Ok(Value::String(string("trim", &args[0])?.trim().to_string()))
+}
+
+fn to_upper(_: &mut Inter, args: &[Value]) -> Result<Value> {
+    Ok(Value::String(string("to_upper", &args[0])?.to_uppercase()))
+}
+
+// Whether a string contains a substring, or a map contains a key
+fn contains(_: &mut Inter, args: &[Value]) -> Result<Value> {
+    if let Value::Map(entries) = &args[0] {
+        let key = key("contains", &args[1])?;
+
+        return Ok(Value::Bool(entries.borrow().contains_key(&key)));
+    }
+
+    let needle = string("contains", &args[1])?;
+
+    Ok(Value::Bool(string("contains", &args[0])?.contains(needle)))
+}
+
+// An empty separator splits the string into its chars
+fn split(_: &mut Inter, args: &[Value]) -> Result<Value> {
+    let text = string("split", &args[0])?;
+
+    let parts = match string("split", &args[1])? {
+        "" => text.chars().map(|c| Value::String(c.to_string())).collect(),
+        sep => text
+            .split(sep)
+            .map(|part| Value::String(part.to_string()))
+            .collect(),
+    };
+
+    Ok(Value::list(parts))
+}
+
+// Returns the new length of the list
+fn push(_: &mut Inter, args: &[Value]) -> Result<Value> {
+    let mut items = list("push", &args[0])?.borrow_mut();
+
+    items.push(args[1].clone());
+
+    Ok(Value::from(items.len()))
+}
+
+fn pop(_: &mut Inter, args: &[Value]) -> Result<Value> {
+    list("pop", &args[0])?
+        .borrow_mut()
+        .pop()
+        .ok_or(ErrorKind::IndexOutOfBounds {
+            index: Value::Int(-1),
+            len: 0,
+        })
+}
+
+// The keys in order, so a script can loop over a map by index
+fn keys(_: &mut Inter, args: &[Value]) -> Result<Value> {
+    let entries = map("keys", &args[0])?.borrow();
+
+    Ok(Value::list(
+        entries.keys().cloned().map(Value::from).collect(),
+    ))
+}
+
+// Returns the value which was removed
+fn remove(_: &mut Inter, args: &[Value]) -> Result<Value> {
+    let key = key("remove", &args[1])?;
+
+    map("remove", &args[0])?
+        .borrow_mut()
+        .remove(&key)
+        .ok_or_else(|| ErrorKind::KeyNotFound(args[1].clone()))
+}
+
+// Like indexing, but a missing key gives nil rather than an error
+fn get(_: &mut Inter, args: &[Value]) -> Result<Value> {
+    let key = key("get", &args[1])?;
+
+    Ok(map("get", &args[0])?
+        .borrow()
+        .get(&key)
+        .cloned()
+        .unwrap_or(Value::Nil))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        testing::{eval, eval_err, string},
+        vm::ErrorKind as VmErrorKind,
+    };
+
+    use super::Value;
+
+    fn strings(strings: &[&str]) -> Value {
+        Value::list(strings.iter().map(|s| string(s)).collect())
+    }
+
+    #[test]
+    fn len_works() {
+        for (source, len) in &[
+            (r#"len("héllo")"#, 5),
+            ("len([1, [2, 3]])", 2),
+            (r#"len({"a": 1, "b": 2})"#, 2),
+        ] {
+            assert_eq!(eval(source), Value::Int(*len), "{}", source);
+        }
+    }
+
+    #[test]
+    fn len_fails_on_other_values() {
+        assert!(matches!(
+            eval_err("len(1)"),
+            VmErrorKind::InvalidArgument { name, .. } if name == "len"
+        ));
+
+        assert!(matches!(
+            eval_err(r#"len("a", "b")"#),
+            VmErrorKind::ArityMismatch {
+                expected: 1,
+                found: 2,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn string_builtins_work() {
+        assert_eq!(eval(r#"to_upper(trim("  ab "))"#), string("AB"));
+
+        assert_eq!(eval(r#"contains("wörld", "ö")"#), Value::Bool(true));
+
+        assert_eq!(eval(r#"contains("wörld", "x")"#), Value::Bool(false));
+    }
+
+    #[test]
+    fn split_works() {
+        assert_eq!(eval(r#"split("a b", " ")"#), strings(&["a", "b"]));
+
+        assert_eq!(eval(r#"split("a,,b", ",")"#), strings(&["a", "", "b"]));
+
+        // An empty separator splits into chars
+        assert_eq!(eval(r#"split("hé", "")"#), strings(&["h", "é"]));
+    }
+
+    #[test]
+    fn push_and_pop_work() {
+        let source = r#"
+            let xs = [1]
+            let n = push(xs, 2)
+            let last = pop(xs)
+            [n, last, xs]
+        "#;
+
+        assert_eq!(eval(source).to_string(), "[2, 2, [1]]");
+    }
+
+    #[test]
+    fn list_builtins_fail_on_bad_arguments() {
+        assert!(matches!(
+            eval_err("pop([])"),
+            VmErrorKind::IndexOutOfBounds { len: 0, .. }
+        ));
+
+        assert!(matches!(
+            eval_err("push(1, 2)"),
+            VmErrorKind::InvalidArgument { name, .. } if name == "push"
+        ));
+    }
+
+    #[test]
+    fn map_builtins_work() {
+        let source = r#"
+            let m = {"b": 2, 1: "one"}
+            let removed = remove(m, "b")
+            [removed, keys(m), contains(m, 1), get(m, "b")]
+        "#;
+
+        assert_eq!(eval(source).to_string(), r#"[2, [1], true, nil]"#);
+    }
+
+    #[test]
+    fn map_builtins_fail_on_bad_arguments() {
+        assert!(matches!(
+            eval_err("remove({}, 1)"),
+            VmErrorKind::KeyNotFound(Value::Int(1))
+        ));
+
+        assert!(matches!(
+            eval_err("keys([])"),
+            VmErrorKind::InvalidArgument { name, .. } if name == "keys"
+        ));
+
+        assert!(matches!(
+            eval_err("get({}, [])"),
+            VmErrorKind::InvalidArgument { name, .. } if name == "get"
+        ));
+    }
+}
@@ -1,10 +1,17 @@
-use std::{collections::HashMap, default};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    rc::Rc,
+};
 
 use super::{
+    bigint::BigInt,
     frame::{Frame, Scope},
+    function::Closure,
     instr::Instr,
     instr::{BinopKind, CompareKind, UnaryKind},
-    stack::{Stack, StackKind},
+    native::Native,
+    stack::{Limits, Stack, StackKind},
     value::Value,
     ErrorKind, Result,
 };
@@ -15,18 +22,25 @@ pub(crate) struct Evaluator {
     pub(crate) running: bool,
     pub(crate) globals: HashMap<String, Value>,
     pub(crate) frames: Stack<Frame>,
+    pub(crate) limits: Limits,
+
+    // A call to a native made by the last instruction. Natives are given the whole interpreter,
+    // so the interpreter makes the call once the instruction is done.
+    pub(crate) native_call: Option<(Rc<Native>, Vec<Value>)>,
 }
 
 impl Evaluator {
-    pub(crate) fn new() -> Result<Self> {
+    pub(crate) fn new(limits: Limits) -> Result<Self> {
         let mut evaler = Self {
             pc: 0,
             running: true,
             globals: HashMap::default(),
-            frames: Stack::new(StackKind::Frame),
+            frames: Stack::new(StackKind::Frame, &limits),
+            limits,
+            native_call: None,
         };
 
-        evaler.frames.push(Frame::new()?)?;
+        evaler.frames.push(Frame::new(&limits)?)?;
 
         Ok(evaler)
     }
@@ -36,30 +50,90 @@ impl Evaluator {
 
         let frame = self.frames.top_mut()?;
 
+        let divide_by_zero = |kind: BinopKind| ErrorKind::DivisionByZero(kind.symbol());
+
         match *instr {
             Instr::Binop(kind) => match kind {
-                BinopKind::Plus => {
-                    Evaluator::eval_num_binop(&mut frame.vals, instr, |l, r| Value::Int(l + r))
-                }
+                BinopKind::Plus => match frame.vals.stack.as_mut_slice() {
+                    // Strings are concatenated, by appending the right to the left in place
+                    [.., Value::String(l), Value::String(r)] => {
+                        let r = std::mem::take(r);
 
-                BinopKind::Minus => {
-                    Evaluator::eval_num_binop(&mut frame.vals, instr, |l, r| Value::Int(l - r))
-                }
+                        l.push_str(&r);
 
-                BinopKind::Times => {
-                    Evaluator::eval_num_binop(&mut frame.vals, instr, |l, r| Value::Int(l * r))
-                }
+                        frame.vals.pop().map(|_| ())
+                    }
+                    _ => Evaluator::eval_num_binop(
+                        &mut frame.vals,
+                        kind.symbol(),
+                        i32::checked_add,
+                        |l, r| Ok(l + r),
+                        |l, r| Ok(l + r),
+                    ),
+                },
 
-                BinopKind::Divide => {
-                    Evaluator::eval_num_binop(&mut frame.vals, instr, |l, r| Value::Int(l / r))
-                }
+                BinopKind::Minus => Evaluator::eval_num_binop(
+                    &mut frame.vals,
+                    kind.symbol(),
+                    i32::checked_sub,
+                    |l, r| Ok(l - r),
+                    |l, r| Ok(l - r),
+                ),
+
+                BinopKind::Times => Evaluator::eval_num_binop(
+                    &mut frame.vals,
+                    kind.symbol(),
+                    i32::checked_mul,
+                    |l, r| Ok(l * r),
+                    |l, r| Ok(l * r),
+                ),
+
+                BinopKind::Divide => Evaluator::eval_num_binop(
+                    &mut frame.vals,
+                    kind.symbol(),
+                    i32::checked_div,
+                    |l, r| {
+                        l.div_rem(r)
+                            .map(|(quotient, _)| quotient)
+                            .ok_or_else(|| divide_by_zero(kind))
+                    },
+                    |l, r| {
+                        if r == 0.0 {
+                            Err(divide_by_zero(kind))
+                        } else {
+                            Ok(l / r)
+                        }
+                    },
+                ),
+
+                BinopKind::Modulo => Evaluator::eval_num_binop(
+                    &mut frame.vals,
+                    kind.symbol(),
+                    i32::checked_rem,
+                    |l, r| {
+                        l.div_rem(r)
+                            .map(|(_, remainder)| remainder)
+                            .ok_or_else(|| divide_by_zero(kind))
+                    },
+                    |l, r| {
+                        if r == 0.0 {
+                            Err(divide_by_zero(kind))
+                        } else {
+                            Ok(l % r)
+                        }
+                    },
+                ),
 
                 BinopKind::And => {
-                    Evaluator::eval_bool_binop(&mut frame.vals, instr, |l, r| Value::Bool(l && r))
+                    Evaluator::eval_bool_binop(&mut frame.vals, kind.symbol(), |l, r| {
+                        Value::Bool(l && r)
+                    })
                 }
 
                 BinopKind::Or => {
-                    Evaluator::eval_bool_binop(&mut frame.vals, instr, |l, r| Value::Bool(l || r))
+                    Evaluator::eval_bool_binop(&mut frame.vals, kind.symbol(), |l, r| {
+                        Value::Bool(l || r)
+                    })
                 }
             },
 
@@ -67,7 +141,20 @@ impl Evaluator {
                 UnaryKind::Not => match frame.vals.pop()? {
                     Value::Bool(val) => frame.vals.push(Value::Bool(!val)),
                     val => Err(ErrorKind::InvalidUnary {
-                        instr: instr.clone(),
+                        op: kind.symbol(),
+                        val,
+                    }),
+                },
+
+                UnaryKind::Negate => match frame.vals.pop()? {
+                    Value::Float(val) => frame.vals.push(Value::Float(-val)),
+                    Value::Int(val) => frame.vals.push(match val.checked_neg() {
+                        Some(val) => Value::Int(val),
+                        None => Value::from_big(-&BigInt::from(val)),
+                    }),
+                    Value::BigInt(val) => frame.vals.push(Value::from_big(-&val)),
+                    val => Err(ErrorKind::InvalidUnary {
+                        op: kind.symbol(),
                         val,
                     }),
                 },
@@ -100,20 +187,26 @@ impl Evaluator {
                 Ok(())
             }
             Instr::Compare(kind) => {
-                let l = frame.vals.pop()?;
-
+                // The right operand is pushed last, so it is popped first
                 let r = frame.vals.pop()?;
 
-                match kind {
-                    CompareKind::Equal => frame.vals.push(Value::Bool(l == r))?,
-                    CompareKind::NotEqual => frame.vals.push(Value::Bool(l != r))?,
-                    CompareKind::LessThan => frame.vals.push(Value::Bool(l < r))?,
-                    CompareKind::LassThanOrEqual => frame.vals.push(Value::Bool(l <= r))?,
-                    CompareKind::GreaterThan => frame.vals.push(Value::Bool(l > r))?,
-                    CompareKind::GreaterThanOrEqual => frame.vals.push(Value::Bool(l >= r))?,
+                let l = frame.vals.pop()?;
+
+                let result = match kind {
+                    CompareKind::Equal => l.equals(&r),
+                    CompareKind::NotEqual => !l.equals(&r),
+                    // Unordered values, like NaN, are never less or greater
+                    CompareKind::LessThan => l.compare(&r)? == Some(Ordering::Less),
+                    CompareKind::LessThanOrEqual => {
+                        matches!(l.compare(&r)?, Some(Ordering::Less | Ordering::Equal))
+                    }
+                    CompareKind::GreaterThan => l.compare(&r)? == Some(Ordering::Greater),
+                    CompareKind::GreaterThanOrEqual => {
+                        matches!(l.compare(&r)?, Some(Ordering::Greater | Ordering::Equal))
+                    }
                 };
 
-                Ok(())
+                frame.vals.push(Value::Bool(result))
             }
             Instr::PopJumpFalse(new_pc) => self.eval_pop_jump(new_pc, |val| !val),
             Instr::PopJumpTrue(new_pc) => self.eval_pop_jump(new_pc, |val| val),
@@ -122,19 +215,20 @@ impl Evaluator {
                 //frame.blocks.top_mut()?.locals.insert(name.to_string(), frame.vals.pop()?);
                 let top = frame.vals.pop()?;
 
-                match frame.get_local_mut(name) {
+                match frame.get_cell(name) {
                     Some(local) => {
-                        *local = top;
-
-                        Ok(())
-                    }
-                    None => {
-                        frame.blocks.top_mut()?.locals.insert(name.to_string(), top);
+                        *local.borrow_mut() = top;
 
                         Ok(())
                     }
+                    None => frame.declare(name, top),
                 }
             }
+            Instr::Declare(ref name) => {
+                let top = frame.vals.pop()?;
+
+                frame.declare(name, top)
+            }
             Instr::StoreGlobal(ref name) => {
                 // TODO: Try and remove clone() here
                 self.globals.insert(name.to_string(), frame.vals.pop()?);
@@ -142,8 +236,7 @@ impl Evaluator {
                 Ok(())
             }
             Instr::Load(ref name) => {
-                // Clone here to prevent compiler errors
-                let val = frame.get_local(name).and_then(|val| Some(val.clone()));
+                let val = frame.get_local(name);
 
                 match val {
                     Some(val) => frame.vals.push(val),
@@ -177,36 +270,201 @@ impl Evaluator {
 
                 Ok(())
             }
+
+            Instr::List(len) => {
+                let mut items = (0..len)
+                    .map(|_| frame.vals.pop())
+                    .collect::<Result<Vec<_>>>()?;
+
+                // The last item was on top of the stack
+                items.reverse();
+
+                frame.vals.push(Value::list(items))
+            }
+
+            Instr::Map(len) => {
+                let mut entries = (0..len)
+                    .map(|_| {
+                        let val = frame.vals.pop()?;
+
+                        Ok((frame.vals.pop()?, val))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                // Inserted in the order they were written, so a repeated key keeps its last value
+                entries.reverse();
+
+                let map = Value::map(BTreeMap::new());
+
+                for (key, val) in entries {
+                    map.set_index(&key, val)?;
+                }
+
+                frame.vals.push(map)
+            }
+
+            Instr::Index => {
+                let index = frame.vals.pop()?;
+
+                let val = frame.vals.pop()?;
+
+                frame.vals.push(val.index(&index)?)
+            }
+
+            Instr::StoreIndex => {
+                let item = frame.vals.pop()?;
+
+                let index = frame.vals.pop()?;
+
+                frame.vals.pop()?.set_index(&index, item)
+            }
+
+            Instr::Slice(has_start, has_end) => {
+                let end = has_end.then(|| frame.vals.pop()).transpose()?;
+
+                let start = has_start.then(|| frame.vals.pop()).transpose()?;
+
+                let val = frame.vals.pop()?;
+
+                frame.vals.push(val.slice(start.as_ref(), end.as_ref())?)
+            }
+
+            Instr::Call(argc) => self.eval_call(argc),
+
+            Instr::Closure(ref function) => {
+                let upvalues = function
+                    .captures
+                    .iter()
+                    .map(|name| match frame.get_cell(name) {
+                        Some(local) => Ok((name.to_string(), Rc::clone(local))),
+                        None => Err(ErrorKind::UnknownConst(name.to_string())),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                let closure = Closure::new(Rc::clone(function), upvalues);
+
+                frame.vals.push(Value::Closure(Rc::new(closure)))
+            }
+
+            Instr::Return => {
+                let mut callee = self.frames.pop()?;
+
+                self.pc = callee.return_pc;
+
+                // The compiler always pushes a value to return, nil if the function gave none
+                let val = callee.vals.pop()?;
+
+                self.frames.top_mut()?.vals.push(val)
+            }
         }
     }
 
-    fn eval_bool_binop<F>(stack: &mut Stack<Value>, instr: &Instr, eval_fn: F) -> Result
+    fn eval_call(&mut self, argc: usize) -> Result {
+        let frame = self.frames.top_mut()?;
+
+        let mut args = (0..argc)
+            .map(|_| frame.vals.pop())
+            .collect::<Result<Vec<_>>>()?;
+
+        // The last argument was on top of the stack
+        args.reverse();
+
+        let closure = match frame.vals.pop()? {
+            Value::Closure(closure) => closure,
+            Value::Native(native) => {
+                if native.arity != argc {
+                    return Err(ErrorKind::ArityMismatch {
+                        name: native.name.to_string(),
+                        expected: native.arity,
+                        found: argc,
+                    });
+                }
+
+                // Natives don't get a frame of their own, their result is pushed straight away
+                self.native_call = Some((native, args));
+
+                return Ok(());
+            }
+            val => return Err(ErrorKind::InvalidCall(val)),
+        };
+
+        let function = &closure.function;
+
+        if function.params.len() != argc {
+            return Err(ErrorKind::ArityMismatch {
+                name: function.name().to_string(),
+                expected: function.params.len(),
+                found: argc,
+            });
+        }
+
+        let mut callee = Frame::call(Rc::clone(function), self.pc, &self.limits)?;
+
+        // The captured locals are shared, rather than copied, into the callee's frame
+        callee
+            .blocks
+            .top_mut()?
+            .locals
+            .extend(closure.upvalues.iter().cloned());
+
+        // A named function can always see itself, so that it can recurse
+        if let Some(name) = &function.name {
+            callee.declare(name, Value::Closure(Rc::clone(&closure)))?;
+        }
+
+        for (param, arg) in function.params.iter().zip(args) {
+            callee.declare(param, arg)?;
+        }
+
+        self.pc = function.entry;
+
+        self.frames.push(callee)
+    }
+
+    fn eval_bool_binop<F>(stack: &mut Stack<Value>, op: &'static str, eval_fn: F) -> Result
     where
         F: FnOnce(bool, bool) -> Value,
     {
-        match (stack.pop()?, stack.pop()?) {
+        let (r, l) = (stack.pop()?, stack.pop()?);
+
+        match (l, r) {
             (Value::Bool(l), Value::Bool(r)) => stack.push(eval_fn(l, r)),
-            (l, r) => Err(ErrorKind::InvalidBinop {
-                instr: instr.clone(),
-                l,
-                r,
-            }),
+            (l, r) => Err(ErrorKind::InvalidBinop { op, l, r }),
         }
     }
 
-    fn eval_num_binop<F>(stack: &mut Stack<Value>, instr: &Instr, eval_fn: F) -> Result
+    // Ints which don't fit into an Int are worked out as big ints, but if either operand is a
+    // float then both are promoted to floats
+    fn eval_num_binop<I, B, F>(
+        stack: &mut Stack<Value>,
+        op: &'static str,
+        int_fn: I,
+        big_fn: B,
+        float_fn: F,
+    ) -> Result
     where
-        F: FnOnce(i32, i32) -> Value,
+        I: FnOnce(i32, i32) -> Option<i32>,
+        B: FnOnce(&BigInt, &BigInt) -> Result<BigInt>,
+        F: FnOnce(f64, f64) -> Result<f64>,
     {
-        match (stack.pop()?, stack.pop()?) {
-            (Value::Int(l), Value::Int(r)) => stack.push(eval_fn(l, r)),
-            // TODO: Try and remove the clone here
-            (l, r) => Err(ErrorKind::InvalidBinop {
-                instr: instr.clone(),
-                l,
-                r,
-            }),
-        }
+        let (r, l) = (stack.pop()?, stack.pop()?);
+
+        let val = match (&l, &r) {
+            (Value::Int(l), Value::Int(r)) => match int_fn(*l, *r) {
+                Some(val) => Value::Int(val),
+                None => Value::from_big(big_fn(&BigInt::from(*l), &BigInt::from(*r))?),
+            },
+            (Value::Int(l), Value::BigInt(r)) => Value::from_big(big_fn(&BigInt::from(*l), r)?),
+            (Value::BigInt(l), Value::Int(r)) => Value::from_big(big_fn(l, &BigInt::from(*r))?),
+            (Value::BigInt(l), Value::BigInt(r)) => Value::from_big(big_fn(l, r)?),
+            _ => match (l.to_f64(), r.to_f64()) {
+                (Some(l), Some(r)) => Value::Float(float_fn(l, r)?),
+                // TODO: Try and remove the clone here
+                _ => return Err(ErrorKind::InvalidBinop { op, l, r }),
+            },
+        };
+
+        stack.push(val)
     }
 
     fn eval_pop_jump<F>(&mut self, new_pc: usize, eval_fn: F) -> Result
@@ -1,11 +1,15 @@
-use std::collections::HashMap;
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
 use super::{
-    stack::{Stack, StackKind},
+    function::Function,
+    stack::{Limits, Stack, StackKind},
     value::Value,
     Result,
 };
 
+// Locals are shared with the closures which capture them
+pub(crate) type Local = Rc<RefCell<Value>>;
+
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct Scope {
     // Represents the index of the stack at which the block is pushed
@@ -15,7 +19,7 @@ pub(crate) struct Scope {
     pub(crate) after_instr: usize,
 
     //pub(crate) locals: HashMap<String, Value>,
-    pub(crate) locals: HashMap<String, Value>,
+    pub(crate) locals: HashMap<String, Local>,
 }
 
 impl Scope {
@@ -32,13 +36,21 @@ impl Scope {
 pub(crate) struct Frame {
     pub(crate) vals: Stack<Value>,
     pub(crate) blocks: Stack<Scope>,
+
+    // The function this frame is running, the top frame isn't running one
+    pub(crate) function: Option<Rc<Function>>,
+
+    // Where the caller continues once the function returns
+    pub(crate) return_pc: usize,
 }
 
 impl Frame {
-    pub(crate) fn new() -> Result<Self> {
+    pub(crate) fn new(limits: &Limits) -> Result<Self> {
         let mut frame = Self {
-            vals: Stack::new(StackKind::Value),
-            blocks: Stack::new(StackKind::Scope),
+            vals: Stack::new(StackKind::Value, limits),
+            blocks: Stack::new(StackKind::Scope, limits),
+            function: None,
+            return_pc: 0,
         };
 
         // The frame needs an initial scope. After-instr is not needed, I think
@@ -47,41 +59,33 @@ impl Frame {
         Ok(frame)
     }
 
-    pub(crate) fn get_local(&self, name: &String) -> Option<&Value> {
-        for block in self.blocks.stack.iter().rev() {
-            if let Some(val) = block.locals.get(name) {
-                return Some(val);
-            }
-        }
-
-        None
-
-        /*
-        let mut top_index = self.blocks.len() - 1;
-
-        let mut top = self.blocks.get(top_index);
-
-        while let Some(top_block) = top {
-            if let Some(val) = top_block.locals.get(name) {
-                return Some(val);
-            }
-
-            top_index -= 1;
+    pub(crate) fn call(function: Rc<Function>, return_pc: usize, limits: &Limits) -> Result<Self> {
+        Ok(Self {
+            function: Some(function),
+            return_pc,
+            ..Frame::new(limits)?
+        })
+    }
 
-            top = self.blocks.get(top_index);
-        }
+    pub(crate) fn get_local(&self, name: &str) -> Option<Value> {
+        self.get_cell(name).map(|local| local.borrow().clone())
+    }
 
-        None
-        */
+    pub(crate) fn get_cell(&self, name: &str) -> Option<&Local> {
+        self.blocks
+            .stack
+            .iter()
+            .rev()
+            .find_map(|block| block.locals.get(name))
     }
 
-    pub(crate) fn get_local_mut(&mut self, name: &String) -> Option<&mut Value> {
-        for block in self.blocks.stack.iter_mut().rev() {
-            if let Some(val) = block.locals.get_mut(name) {
-                return Some(val);
-            }
-        }
+    // Binds a new local in the innermost scope, shadowing any local with the same name
+    pub(crate) fn declare(&mut self, name: &str, val: Value) -> Result {
+        self.blocks
+            .top_mut()?
+            .locals
+            .insert(name.to_string(), Rc::new(RefCell::new(val)));
 
-        None
+        Ok(())
     }
 }
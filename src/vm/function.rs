@@ -0,0 +1,81 @@
+use std::{fmt, rc::Rc};
+
+use super::frame::Local;
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Function {
+    // Anonymous functions have no name
+    pub(crate) name: Option<String>,
+    pub(crate) params: Vec<String>,
+
+    // Address of the first instruction of the body
+    pub(crate) entry: usize,
+
+    // Locals of the enclosing functions which the body uses, captured when the closure is made
+    pub(crate) captures: Vec<String>,
+}
+
+impl Function {
+    pub(crate) fn new(
+        name: Option<&str>,
+        params: &[String],
+        entry: usize,
+        captures: &[String],
+    ) -> Self {
+        Function {
+            name: name.map(str::to_string),
+            params: params.to_vec(),
+            entry,
+            captures: captures.to_vec(),
+        }
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or("anonymous")
+    }
+}
+
+// A function along with the locals it captured. The locals are shared with the frame which
+// defined them, so changes are seen by both, and live on after the frame is popped.
+#[derive(Clone)]
+pub struct Closure {
+    pub(crate) function: Rc<Function>,
+    pub(crate) upvalues: Vec<(String, Local)>,
+}
+
+impl Closure {
+    pub(crate) fn new(function: Rc<Function>, upvalues: Vec<(String, Local)>) -> Self {
+        Closure { function, upvalues }
+    }
+}
+
+// Closures are compared by identity, a closure can capture itself so comparing the captured
+// values might never finish
+impl PartialEq for Closure {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.function, &other.function)
+            && self.upvalues.len() == other.upvalues.len()
+            && self
+                .upvalues
+                .iter()
+                .zip(&other.upvalues)
+                .all(|((_, l), (_, r))| Rc::ptr_eq(l, r))
+    }
+}
+
+// Only the names of the captured locals are shown, for the same reason as above
+impl fmt::Debug for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Closure")
+            .field("function", &self.function)
+            .field(
+                "upvalues",
+                &self
+                    .upvalues
+                    .iter()
+                    .map(|(name, _)| name)
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
@@ -1,11 +1,13 @@
-use super::value::Value;
+use std::{fmt, rc::Rc};
+
+use super::{function::Function, value::Value};
 
 #[derive(Copy, Clone, Debug)]
 pub(crate) enum CompareKind {
     Equal,
     NotEqual,
     LessThan,
-    LassThanOrEqual,
+    LessThanOrEqual,
     GreaterThan,
     GreaterThanOrEqual,
 }
@@ -16,13 +18,19 @@ pub(crate) enum BinopKind {
     Minus,
     Times,
     Divide,
+    Modulo,
+    // The compiler short-circuits `&&` and `||` with jumps, so these are only for hand-written
+    // instructions, which evaluate both sides
+    #[allow(dead_code)]
     And,
+    #[allow(dead_code)]
     Or,
 }
 
 #[derive(Copy, Clone, Debug)]
 pub(crate) enum UnaryKind {
     Not,
+    Negate,
 }
 
 #[derive(Clone, Debug)]
@@ -30,6 +38,8 @@ pub(crate) enum Instr {
     Binop(BinopKind),
     Unary(UnaryKind),
     Print,
+    // Stops the interpreter early. The compiler doesn't emit it, scripts just run to the end.
+    #[allow(dead_code)]
     Exit,
     Push(Value),
     Pop,
@@ -38,17 +48,103 @@ pub(crate) enum Instr {
     PopJumpFalse(usize),
     PopJumpTrue(usize),
     Store(String),
+    // Binds a new local in the innermost scope, rather than updating an existing one
+    Declare(String),
     StoreGlobal(String),
     Load(String),
     PushScope(usize),
     PopScope,
+    // Calls the function below the given number of arguments on the stack
+    Call(usize),
+    Return,
+    // Pushes the function, capturing its upvalues from the current frame
+    Closure(Rc<Function>),
+    // Pops the given number of items into a new list, the first item being the deepest
+    List(usize),
+    // Pops the given number of keys and values into a new map, each value above its key
+    Map(usize),
+    // Pops the index then the value being indexed
+    Index,
+    // Pops the new item, the index and then the list or map
+    StoreIndex,
+    // Like Index, but with the end and then the start popped, if they were given
+    Slice(bool, bool),
+}
+
+// Operators are shown as they're written in the source, for error messages
+impl fmt::Display for Instr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instr::Binop(kind) => write!(f, "{}", kind),
+            Instr::Unary(kind) => write!(f, "{}", kind),
+            Instr::Compare(kind) => write!(f, "{}", kind),
+            instr => write!(f, "{:?}", instr),
+        }
+    }
+}
+
+// The operators as they're written in scripts, which is how errors refer to them
+impl BinopKind {
+    pub(crate) fn symbol(self) -> &'static str {
+        match self {
+            BinopKind::Plus => "+",
+            BinopKind::Minus => "-",
+            BinopKind::Times => "*",
+            BinopKind::Divide => "/",
+            BinopKind::Modulo => "%",
+            BinopKind::And => "&&",
+            BinopKind::Or => "||",
+        }
+    }
+}
+
+impl UnaryKind {
+    pub(crate) fn symbol(self) -> &'static str {
+        match self {
+            UnaryKind::Not => "!",
+            UnaryKind::Negate => "-",
+        }
+    }
+}
+
+impl CompareKind {
+    pub(crate) fn symbol(self) -> &'static str {
+        match self {
+            CompareKind::Equal => "==",
+            CompareKind::NotEqual => "!=",
+            CompareKind::LessThan => "<",
+            CompareKind::LessThanOrEqual => "<=",
+            CompareKind::GreaterThan => ">",
+            CompareKind::GreaterThanOrEqual => ">=",
+        }
+    }
+}
+
+impl fmt::Display for BinopKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+impl fmt::Display for UnaryKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+impl fmt::Display for CompareKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{
-        vm::{frame::Frame, Result}, 
-        Inter
+    use crate::vm::{
+        frame::Frame,
+        inter::Inter,
+        stack::{Limits, StackErrorKind, StackKind},
+        ErrorKind, Result,
     };
 
     use super::{BinopKind, CompareKind, Instr, UnaryKind, Value};
@@ -56,7 +152,7 @@ mod test {
     fn test_instrs(instrs: &[Instr]) -> Result<Inter> {
         let mut inter = Inter::new()?;
 
-        inter.push_instrs(instrs);
+        inter.push_instrs(instrs, &[]);
 
         inter.run()?;
 
@@ -64,11 +160,7 @@ mod test {
     }
 
     pub(crate) fn top_frame(inter: &Inter) -> Result<&Frame> {
-        inter.frames.top()
-    }
-
-    fn top_frame_mut(inter: &mut Inter) -> Result<&mut Frame> {
-        inter.frames.top_mut()
+        inter.evaler.frames.top()
     }
 
     #[test]
@@ -131,6 +223,12 @@ mod test {
             Instr::Binop(BinopKind::Divide),
         ])?;
 
+        let modulo_inter = test_instrs(&[
+            Instr::Push(Value::Int(400)),
+            Instr::Push(Value::Int(300)),
+            Instr::Binop(BinopKind::Modulo),
+        ])?;
+
         let and_inter = test_instrs(&[
             Instr::Push(Value::Bool(true)),
             Instr::Push(Value::Bool(true)),
@@ -151,6 +249,8 @@ mod test {
 
         assert_eq!(top_frame(&divide_inter)?.vals.top()?, &Value::Int(1));
 
+        assert_eq!(top_frame(&modulo_inter)?.vals.top()?, &Value::Int(100));
+
         assert_eq!(top_frame(&and_inter)?.vals.top()?, &Value::Bool(true));
 
         assert_eq!(top_frame(&or_inter)?.vals.top()?, &Value::Bool(true));
@@ -158,13 +258,39 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn binop_operand_order_works() -> Result {
+        let minus_inter = test_instrs(&[
+            Instr::Push(Value::Int(400)),
+            Instr::Push(Value::Int(100)),
+            Instr::Binop(BinopKind::Minus),
+        ])?;
+
+        let less_than_inter = test_instrs(&[
+            Instr::Push(Value::Int(100)),
+            Instr::Push(Value::Int(400)),
+            Instr::Compare(CompareKind::LessThan),
+        ])?;
+
+        assert_eq!(top_frame(&minus_inter)?.vals.top()?, &Value::Int(300));
+
+        assert_eq!(top_frame(&less_than_inter)?.vals.top()?, &Value::Bool(true));
+
+        Ok(())
+    }
+
     #[test]
     fn unary_works() -> Result {
         let not_inter =
             test_instrs(&[Instr::Push(Value::Bool(true)), Instr::Unary(UnaryKind::Not)])?;
 
+        let negate_inter =
+            test_instrs(&[Instr::Push(Value::Int(400)), Instr::Unary(UnaryKind::Negate)])?;
+
         assert_eq!(top_frame(&not_inter)?.vals.top()?, &Value::Bool(false));
 
+        assert_eq!(top_frame(&negate_inter)?.vals.top()?, &Value::Int(-400));
+
         Ok(())
     }
 
@@ -263,7 +389,7 @@ mod test {
             .blocks
             .top()?
             .locals
-            .contains_key("x".into()));
+            .contains_key("x"));
 
         Ok(())
     }
@@ -275,7 +401,7 @@ mod test {
          */
         let inter = test_instrs(&[Instr::Push(Value::Int(400)), Instr::StoreGlobal("x".into())])?;
 
-        assert!(inter.evaler.globals.contains_key("x".into()));
+        assert!(inter.evaler.globals.contains_key("x"));
 
         Ok(())
     }
@@ -336,7 +462,7 @@ mod test {
             .blocks
             .top()?
             .locals
-            .contains_key("i".into()));
+            .contains_key("i"));
 
         Ok(())
     }
@@ -354,4 +480,143 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn stack_limits_work() -> Result {
+        let mut inter = Inter::with_limits(Limits {
+            values: 2,
+            ..Limits::default()
+        })?;
+
+        inter.push_instrs(
+            &[
+                Instr::Push(Value::Int(1)),
+                Instr::Push(Value::Int(2)),
+                Instr::Push(Value::Int(3)),
+            ],
+            &[],
+        );
+
+        assert!(matches!(
+            inter.run(),
+            Err(ErrorKind::StackError(
+                StackKind::Value,
+                StackErrorKind::StackOverflow
+            ))
+        ));
+
+        assert!(matches!(
+            test_instrs(&[Instr::Pop]),
+            Err(ErrorKind::StackError(
+                StackKind::Value,
+                StackErrorKind::StackUnderflow
+            ))
+        ));
+
+        assert!(matches!(
+            test_instrs(&[Instr::PopScope, Instr::PopScope]),
+            Err(ErrorKind::StackError(
+                StackKind::Scope,
+                StackErrorKind::StackUnderflow
+            ))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn checked_arithmetic_works() -> Result {
+        let overflow = test_instrs(&[
+            Instr::Push(Value::Int(i32::MAX)),
+            Instr::Push(Value::Int(1)),
+            Instr::Binop(BinopKind::Plus),
+        ])?;
+
+        let negate_overflow = test_instrs(&[
+            Instr::Push(Value::Int(i32::MIN)),
+            Instr::Unary(UnaryKind::Negate),
+        ])?;
+
+        // Results which fit are demoted back to ints
+        let demoted = test_instrs(&[
+            Instr::Push(Value::Int(i32::MAX)),
+            Instr::Push(Value::Int(1)),
+            Instr::Binop(BinopKind::Plus),
+            Instr::Push(Value::Int(2)),
+            Instr::Binop(BinopKind::Minus),
+        ])?;
+
+        let divide_by_zero = test_instrs(&[
+            Instr::Push(Value::Int(1)),
+            Instr::Push(Value::Int(0)),
+            Instr::Binop(BinopKind::Divide),
+        ]);
+
+        let modulo_by_zero = test_instrs(&[
+            Instr::Push(Value::Int(1)),
+            Instr::Push(Value::Int(0)),
+            Instr::Binop(BinopKind::Modulo),
+        ]);
+
+        let big = Value::BigInt("2147483648".parse().unwrap());
+
+        assert_eq!(top_frame(&overflow)?.vals.top()?, &big);
+
+        assert_eq!(top_frame(&negate_overflow)?.vals.top()?, &big);
+
+        assert_eq!(
+            top_frame(&demoted)?.vals.top()?,
+            &Value::Int(i32::MAX - 1)
+        );
+
+        assert!(matches!(
+            divide_by_zero,
+            Err(ErrorKind::DivisionByZero("/"))
+        ));
+
+        assert!(matches!(
+            modulo_by_zero,
+            Err(ErrorKind::DivisionByZero("%"))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn float_arithmetic_works() -> Result {
+        let promoted = test_instrs(&[
+            Instr::Push(Value::Int(1)),
+            Instr::Push(Value::Float(0.5)),
+            Instr::Binop(BinopKind::Plus),
+        ])?;
+
+        let equal = test_instrs(&[
+            Instr::Push(Value::Int(1)),
+            Instr::Push(Value::Float(1.0)),
+            Instr::Compare(CompareKind::Equal),
+        ])?;
+
+        let nan = test_instrs(&[
+            Instr::Push(Value::Float(f64::NAN)),
+            Instr::Push(Value::Float(f64::NAN)),
+            Instr::Compare(CompareKind::Equal),
+        ])?;
+
+        assert_eq!(top_frame(&promoted)?.vals.top()?, &Value::Float(1.5));
+
+        assert_eq!(top_frame(&equal)?.vals.top()?, &Value::Bool(true));
+
+        assert_eq!(top_frame(&nan)?.vals.top()?, &Value::Bool(false));
+
+        assert!(matches!(
+            test_instrs(&[
+                Instr::Push(Value::Float(1.0)),
+                Instr::Push(Value::Int(0)),
+                Instr::Binop(BinopKind::Divide),
+            ]),
+            Err(ErrorKind::DivisionByZero(_))
+        ));
+
+        Ok(())
+    }
 }
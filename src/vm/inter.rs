@@ -1,36 +1,145 @@
+use std::{iter, rc::Rc};
+
+use crate::span::Span;
+
 use super::{
+    builtin::BUILTINS,
     eval::Evaluator,
     instr::Instr,
-    Result,
+    native::{Native, NativeFn},
+    stack::Limits,
+    trace::{TraceFrame, Traceback},
+    value::Value,
+    ErrorKind, Result,
 };
 
+// Natives are given the interpreter, and can look at and change its globals through the public
+// methods. The rest is only used by the engine.
 #[derive(Debug)]
-pub(crate) struct Inter {
+pub struct Inter {
     pub(crate) evaler: Evaluator,
     pub(crate) instrs: Vec<Instr>,
+
+    // The span each instruction was compiled from, for tracebacks
+    pub(crate) spans: Vec<Span>,
 }
 
 impl Inter {
+    #[cfg(test)]
     pub(crate) fn new() -> Result<Self> {
-        Ok(Self {
-            evaler: Evaluator::new()?,
+        Self::with_limits(Limits::default())
+    }
+
+    pub(crate) fn with_limits(limits: Limits) -> Result<Self> {
+        let mut inter = Self {
+            evaler: Evaluator::new(limits)?,
             instrs: vec![],
-        })
+            spans: vec![],
+        };
+
+        for builtin in BUILTINS {
+            inter.register(builtin.name, builtin.arity, builtin.func);
+        }
+
+        Ok(inter)
+    }
+
+    // Binds the native to a global, replacing any global of the same name
+    pub fn register(&mut self, name: &str, arity: usize, func: NativeFn) {
+        self.set_global(name, Value::Native(Rc::new(Native::new(name, arity, func))));
     }
 
+    pub fn global(&self, name: &str) -> Option<&Value> {
+        self.evaler.globals.get(name)
+    }
+
+    pub fn set_global(&mut self, name: &str, val: Value) {
+        self.evaler.globals.insert(name.to_string(), val);
+    }
+
+    // The engine uses run_traced, tests only care about the error
+    #[cfg(test)]
     pub(crate) fn run(&mut self) -> Result {
+        self.run_traced().map_err(|(err, _)| err)
+    }
+
+    // Like run, but an error comes with the calls which were active when it happened. The frames
+    // are left as they were, so unwind is still needed afterwards.
+    pub(crate) fn run_traced(&mut self) -> std::result::Result<(), (ErrorKind, Traceback)> {
         while self.evaler.pc < self.instrs.len() && self.evaler.running {
-            //let top_frame = self.frames.top_mut()?;
+            let pc = self.evaler.pc;
 
-            if let Some(instr) = self.instrs.get(self.evaler.pc) {
-                self.evaler.eval(instr)?
+            if let Err(err) = self.step() {
+                return Err((err, self.traceback(pc)));
             }
         }
 
         Ok(())
     }
 
-    pub(crate) fn push_instrs(&mut self, instrs: &[Instr]) {
+    fn step(&mut self) -> Result {
+        if let Some(instr) = self.instrs.get(self.evaler.pc) {
+            self.evaler.eval(instr)?
+        }
+
+        if let Some((native, args)) = self.evaler.native_call.take() {
+            let val = native.call(self, &args)?;
+
+            self.evaler.frames.top_mut()?.vals.push(val)?;
+        }
+
+        Ok(())
+    }
+
+    fn traceback(&self, pc: usize) -> Traceback {
+        let frames = &self.evaler.frames.stack;
+
+        // Each frame but the innermost is at the call which pushed the next frame, just before
+        // where that one returns to
+        let pcs = frames
+            .iter()
+            .skip(1)
+            .map(|frame| frame.return_pc - 1)
+            .chain(iter::once(pc));
+
+        Traceback {
+            frames: frames
+                .iter()
+                .zip(pcs)
+                .map(|(frame, pc)| TraceFrame {
+                    function: frame
+                        .function
+                        .as_ref()
+                        .map(|function| function.name().to_string()),
+                    pc,
+                    span: self.spans.get(pc).copied(),
+                })
+                .collect(),
+        }
+    }
+
+    // Abandons whatever was executing when an error occurred, so that instructions pushed
+    // afterwards run from a clean top frame. Globals and top level locals are kept.
+    pub(crate) fn unwind(&mut self) -> Result {
+        self.evaler.pc = self.instrs.len();
+
+        self.evaler.running = true;
+
+        self.evaler.frames.truncate(1);
+
+        let frame = self.evaler.frames.top_mut()?;
+
+        frame.blocks.truncate(1);
+
+        frame.vals.truncate(0);
+
+        Ok(())
+    }
+
+    // Instructions pushed without spans, as in tests, have no position in tracebacks
+    pub(crate) fn push_instrs(&mut self, instrs: &[Instr], spans: &[Span]) {
+        self.spans.extend_from_slice(spans);
+
         instrs
             .to_vec()
             .drain(0..)
@@ -1,23 +1,124 @@
+use std::fmt;
+
 use self::{
-    instr::Instr,
     stack::{StackErrorKind, StackKind},
     value::Value,
 };
 
+pub mod bigint;
+pub mod builtin;
 pub mod eval;
 pub mod frame;
+pub mod function;
 pub mod instr;
 pub mod inter;
+pub mod native;
 pub mod stack;
+pub mod trace;
 pub mod value;
 
 type Result<T = ()> = std::result::Result<T, ErrorKind>;
 
 #[derive(Clone, Debug)]
-pub(crate) enum ErrorKind {
+#[non_exhaustive]
+pub enum ErrorKind {
     StackError(StackKind, StackErrorKind),
-    InvalidBinop { instr: Instr, l: Value, r: Value },
-    InvalidUnary { instr: Instr, val: Value },
+    // The operators are given as they're written in scripts, like `+`
+    InvalidBinop { op: &'static str, l: Value, r: Value },
+    InvalidUnary { op: &'static str, val: Value },
+    // Only values of the same type can be ordered, and not all types have an order
+    InvalidCompare { l: Value, r: Value },
     InvalidJumpValue(Value),
     UnknownConst(String),
+    InvalidCall(Value),
+    ArityMismatch {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    DivisionByZero(&'static str),
+    InvalidIndex {
+        val: Value,
+        index: Value,
+    },
+    IndexOutOfBounds {
+        index: Value,
+        len: usize,
+    },
+    KeyNotFound(Value),
+    InvalidArgument {
+        name: String,
+        val: Value,
+    },
+    // Raised by natives registered by the program embedding the interpreter
+    NativeError(String),
+}
+
+// A value along with its type, with strings quoted so that they stand out from the message
+struct Described<'a>(&'a Value);
+
+impl fmt::Display for Described<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Value::Nil => write!(f, "nil"),
+            Value::String(string) => write!(f, "string {:?}", string),
+            Value::Closure(_) | Value::Native(_) => write!(f, "{}", self.0),
+            val => write!(f, "{} {}", val.type_name(), val),
+        }
+    }
 }
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::StackError(kind, err) => write!(f, "{} stack {}", kind, err),
+            ErrorKind::InvalidBinop { op, l, r } => write!(
+                f,
+                "cannot apply `{}` to {} and {}",
+                op,
+                Described(l),
+                Described(r)
+            ),
+            ErrorKind::InvalidUnary { op, val } => {
+                write!(f, "cannot apply `{}` to {}", op, Described(val))
+            }
+            ErrorKind::InvalidCompare { l, r } => {
+                write!(f, "cannot order {} and {}", Described(l), Described(r))
+            }
+            ErrorKind::InvalidJumpValue(val) => {
+                write!(f, "expected a bool condition, found {}", Described(val))
+            }
+            ErrorKind::UnknownConst(name) => write!(f, "unknown name `{}`", name),
+            ErrorKind::InvalidCall(val) => write!(f, "cannot call {}", Described(val)),
+            ErrorKind::ArityMismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "`{}` takes {} argument{} but was given {}",
+                name,
+                expected,
+                if *expected == 1 { "" } else { "s" },
+                found
+            ),
+            ErrorKind::DivisionByZero(op) => write!(f, "`{}` by zero", op),
+            ErrorKind::InvalidIndex { val, index } => write!(
+                f,
+                "cannot index {} with {}",
+                Described(val),
+                Described(index)
+            ),
+            ErrorKind::IndexOutOfBounds { index, len } => {
+                write!(f, "index {} is out of bounds for length {}", index, len)
+            }
+            ErrorKind::KeyNotFound(key) => write!(f, "key {} not found", Described(key)),
+            ErrorKind::InvalidArgument { name, val } => {
+                write!(f, "invalid argument to `{}`: {}", name, Described(val))
+            }
+            ErrorKind::NativeError(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ErrorKind {}
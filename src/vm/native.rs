@@ -0,0 +1,116 @@
+use std::fmt;
+
+use super::{inter::Inter, value::Value, Result};
+
+// The signature of a function written in Rust. It's given the whole interpreter, so that it can
+// look at and change the globals.
+pub type NativeFn = fn(&mut Inter, &[Value]) -> Result<Value>;
+
+// A function written in Rust rather than in the language, either a builtin or one registered by
+// the program embedding the interpreter. Each is bound to a global, so scripts can shadow them.
+pub struct Native {
+    pub(crate) name: String,
+    pub(crate) arity: usize,
+    func: NativeFn,
+}
+
+impl Native {
+    pub(crate) fn new(name: &str, arity: usize, func: NativeFn) -> Self {
+        Native {
+            name: name.to_string(),
+            arity,
+            func,
+        }
+    }
+
+    // The arity has already been checked
+    pub(crate) fn call(&self, inter: &mut Inter, args: &[Value]) -> Result<Value> {
+        (self.func)(inter, args)
+    }
+}
+
+// Natives are compared by name, as registering a native replaces any other of the same name
+impl PartialEq for Native {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl fmt::Debug for Native {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Native")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{vm::ErrorKind as VmErrorKind, Engine, ErrorKind};
+
+    use super::{Inter, Result, Value};
+
+    // Counts its calls in a global, and fails once it's been called twice
+    fn tick(inter: &mut Inter, args: &[Value]) -> Result<Value> {
+        let calls = match inter.global("calls") {
+            Some(Value::Int(calls)) => *calls + 1,
+            _ => 1,
+        };
+
+        if calls > 2 {
+            return Err(VmErrorKind::NativeError("ticked too often".to_string()));
+        }
+
+        inter.set_global("calls", Value::Int(calls));
+
+        Ok(args[0].clone())
+    }
+
+    fn engine() -> Engine {
+        let mut engine = Engine::new().expect("creating engine failed");
+
+        engine.register("tick", 1, tick);
+
+        engine
+    }
+
+    #[test]
+    fn natives_can_use_globals() -> crate::Result {
+        let mut engine = engine();
+
+        assert_eq!(engine.eval("tick(1) + tick(2)")?, Value::Int(3));
+
+        assert_eq!(engine.get("calls"), Some(Value::Int(2)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn natives_can_fail() {
+        assert!(matches!(
+            engine().eval("tick(1)\ntick(2)\ntick(3)"),
+            Err(ErrorKind::VmError(VmErrorKind::NativeError(_), _))
+        ));
+    }
+
+    #[test]
+    fn natives_check_their_arity() {
+        assert!(matches!(
+            engine().eval("tick()"),
+            Err(ErrorKind::VmError(VmErrorKind::ArityMismatch { name, expected: 1, found: 0 }, _))
+                if name == "tick"
+        ));
+    }
+
+    #[test]
+    fn registering_replaces_builtins() -> crate::Result {
+        let mut engine = engine();
+
+        engine.register("len", 1, |_, _| Ok(Value::Int(-1)));
+
+        assert_eq!(engine.eval(r#"len("abc")"#)?, Value::Int(-1));
+
+        Ok(())
+    }
+}
@@ -1,54 +1,99 @@
+use std::fmt;
+
 use super::{ErrorKind, Result};
 
 #[derive(Copy, Clone, Debug)]
-pub(crate) enum StackKind {
+pub enum StackKind {
     Value,
     Frame,
     Scope,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
-pub(crate) enum StackErrorKind {
+#[non_exhaustive]
+pub enum StackErrorKind {
     StackOverflow,
     StackUnderflow,
 }
 
+// The most items each kind of stack can hold, so that runaway scripts fail rather than exhausting
+// the host's memory
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Limits {
+    // Per frame
+    pub values: usize,
+
+    pub frames: usize,
+
+    // Per frame
+    pub scopes: usize,
+}
+
+impl Limits {
+    pub(crate) fn get(&self, kind: StackKind) -> usize {
+        match kind {
+            StackKind::Value => self.values,
+            StackKind::Frame => self.frames,
+            StackKind::Scope => self.scopes,
+        }
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            values: 4096,
+            frames: 1024,
+            scopes: 256,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct Stack<T> {
     pub(crate) stack: Vec<T>,
     kind: StackKind,
+    limit: usize,
 }
 
 impl<T> Stack<T> {
-    pub(crate) fn new(kind: StackKind) -> Stack<T> {
+    pub(crate) fn new(kind: StackKind, limits: &Limits) -> Stack<T> {
         Stack {
             stack: vec![],
             kind,
+            limit: limits.get(kind),
         }
     }
 
     pub(crate) fn push(&mut self, value: T) -> Result {
+        if self.stack.len() >= self.limit {
+            return Err(ErrorKind::StackError(
+                self.kind,
+                StackErrorKind::StackOverflow,
+            ));
+        }
+
         self.stack.push(value);
 
         Ok(())
     }
 
     pub(crate) fn pop(&mut self) -> Result<T> {
-        self.stack.pop().ok_or_else(|| self.determine_stack_error())
+        self.stack.pop().ok_or_else(|| self.underflow())
     }
 
+    #[cfg(test)]
     pub(crate) fn top(&self) -> Result<&T> {
-        self.stack
-            .last()
-            .ok_or_else(|| self.determine_stack_error())
+        self.stack.last().ok_or_else(|| self.underflow())
     }
 
     pub(crate) fn top_mut(&mut self) -> Result<&mut T> {
-        let err = self.determine_stack_error();
+        let err = self.underflow();
 
-        self.stack.last_mut().ok_or_else(|| err)
+        self.stack.last_mut().ok_or(err)
     }
 
+    #[cfg(test)]
     pub(crate) fn is_empty(&self) -> bool {
         self.stack.is_empty()
     }
@@ -61,11 +106,27 @@ impl<T> Stack<T> {
         self.stack.len()
     }
 
-    fn determine_stack_error(&self) -> ErrorKind {
-        if self.is_empty() {
-            ErrorKind::StackError(self.kind, StackErrorKind::StackUnderflow)
-        } else {
-            ErrorKind::StackError(self.kind, StackErrorKind::StackOverflow)
+    // Only pushing can overflow, so anything else failing means the stack is empty
+    fn underflow(&self) -> ErrorKind {
+        ErrorKind::StackError(self.kind, StackErrorKind::StackUnderflow)
+    }
+}
+
+impl fmt::Display for StackKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StackKind::Value => write!(f, "value"),
+            StackKind::Frame => write!(f, "call"),
+            StackKind::Scope => write!(f, "scope"),
+        }
+    }
+}
+
+impl fmt::Display for StackErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StackErrorKind::StackOverflow => write!(f, "overflow"),
+            StackErrorKind::StackUnderflow => write!(f, "underflow"),
         }
     }
 }
@@ -0,0 +1,94 @@
+use std::fmt;
+
+use crate::span::Span;
+
+// Where a runtime error happened, along with the chain of calls which led there
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Traceback {
+    // Outermost first, so the last frame is the one which failed. Errors which didn't happen while
+    // running, like failing to create the interpreter, have no frames.
+    pub(crate) frames: Vec<TraceFrame>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceFrame {
+    // The top level isn't running a function
+    pub(crate) function: Option<String>,
+
+    // The innermost frame is at the instruction which failed, the others at the call they're
+    // waiting on
+    pub(crate) pc: usize,
+
+    // Instructions which weren't compiled from source have no span
+    pub(crate) span: Option<Span>,
+}
+
+impl Traceback {
+    // The address of the instruction which failed
+    pub fn pc(&self) -> Option<usize> {
+        self.frames.last().map(|frame| frame.pc)
+    }
+
+    pub fn frames(&self) -> &[TraceFrame] {
+        &self.frames
+    }
+}
+
+impl TraceFrame {
+    pub fn function(&self) -> Option<&str> {
+        self.function.as_deref()
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    // The 1-based line and column in the source the instruction came from
+    pub fn position(&self) -> Option<(usize, usize)> {
+        self.span.map(|span| (span.line, span.col))
+    }
+}
+
+/*
+ * Renders the frames the way they were called, so the failing one comes last. Runs of the same
+ * frame, as left by deep recursion, are shown once:
+ *
+ * traceback (most recent call last):
+ *   at <script>, line 4, col 1
+ *   at f, line 2, col 12
+ *   ... repeated 3 more times
+ */
+impl fmt::Display for Traceback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "traceback (most recent call last):")?;
+
+        let mut frames = self.frames.iter().peekable();
+
+        while let Some(frame) = frames.next() {
+            write!(f, "\n  {}", frame)?;
+
+            let mut repeats = 0;
+
+            while frames.next_if_eq(&frame).is_some() {
+                repeats += 1;
+            }
+
+            if repeats > 0 {
+                write!(f, "\n  ... repeated {} more times", repeats)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for TraceFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "at {}", self.function().unwrap_or("<script>"))?;
+
+        match self.position() {
+            Some((line, col)) => write!(f, ", line {}, col {}", line, col),
+            None => write!(f, ", pc {}", self.pc),
+        }
+    }
+}
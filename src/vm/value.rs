@@ -1,18 +1,852 @@
-use std::fmt;
+use std::{cell::RefCell, cmp::Ordering, collections::BTreeMap, fmt, rc::Rc};
 
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
-pub(crate) enum Value {
+use super::{bigint::BigInt, function::Closure, native::Native, ErrorKind, Result};
+
+#[derive(Clone)]
+pub enum Value {
     Int(i32),
+    // Only for ints which don't fit into an Int, so each int has one representation
+    BigInt(BigInt),
+    Float(f64),
     Bool(bool),
+    // The absence of a value, returned by functions which don't return anything else
+    Nil,
+    String(String),
+    // Lists are shared, so a change made through one reference is seen by all of them
+    List(Rc<RefCell<Vec<Value>>>),
+    // Shared like lists. The keys are kept in order, so iterating over them is repeatable.
+    Map(Rc<RefCell<BTreeMap<MapKey, Value>>>),
+    Closure(Rc<Closure>),
+    Native(Rc<Native>),
+}
+
+// The values which can be used as keys in a map. Ints are stored as big ints, so that an Int and
+// a BigInt with the same value are the same key.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MapKey {
+    Int(BigInt),
     String(String),
 }
 
+impl Value {
+    pub fn list(items: Vec<Value>) -> Self {
+        Value::List(Rc::new(RefCell::new(items)))
+    }
+
+    pub fn map(entries: BTreeMap<MapKey, Value>) -> Self {
+        Value::Map(Rc::new(RefCell::new(entries)))
+    }
+
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) | Value::BigInt(_) => "int",
+            Value::Float(_) => "float",
+            Value::Bool(_) => "bool",
+            Value::Nil => "nil",
+            Value::String(_) => "string",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Closure(_) | Value::Native(_) => "function",
+        }
+    }
+
+    // Demotes the int to an Int if it fits
+    pub(crate) fn from_big(val: BigInt) -> Self {
+        match val.to_i32() {
+            Some(val) => Value::Int(val),
+            None => Value::BigInt(val),
+        }
+    }
+
+    // Any number as a float, for arithmetic with floats
+    pub(crate) fn to_f64(&self) -> Option<f64> {
+        match self {
+            Value::Int(val) => Some(f64::from(*val)),
+            Value::BigInt(val) => Some(val.to_f64()),
+            Value::Float(val) => Some(*val),
+            _ => None,
+        }
+    }
+
+    /*
+     * Values of any types can be tested for equality, values of different types are never equal.
+     * Numbers are equal by their value, so 1 == 1.0, and lists and maps by their contents.
+     * Functions are only equal to themselves.
+     */
+    pub(crate) fn equals(&self, other: &Value) -> bool {
+        self.equals_in(other, &mut vec![])
+    }
+
+    // Seen holds the pairs of lists or maps being compared. Meeting a pair again means they
+    // contain themselves in the same way, and nothing seen so far differs, so they're equal.
+    fn equals_in(&self, other: &Value, seen: &mut Vec<Pair>) -> bool {
+        match (self, other) {
+            // The same list is always equal to itself, even if it contains itself
+            (Value::List(l), Value::List(r)) if Rc::ptr_eq(l, r) => true,
+            (Value::Map(l), Value::Map(r)) if Rc::ptr_eq(l, r) => true,
+            (Value::List(l), Value::List(r)) => {
+                let pair = pair(l, r);
+
+                if seen.contains(&pair) {
+                    return true;
+                }
+
+                seen.push(pair);
+
+                let (l, r) = (l.borrow(), r.borrow());
+
+                let equal =
+                    l.len() == r.len() && l.iter().zip(r.iter()).all(|(l, r)| l.equals_in(r, seen));
+
+                seen.pop();
+
+                equal
+            }
+            (Value::Map(l), Value::Map(r)) => {
+                let pair = pair(l, r);
+
+                if seen.contains(&pair) {
+                    return true;
+                }
+
+                seen.push(pair);
+
+                let (l, r) = (l.borrow(), r.borrow());
+
+                let equal = l.len() == r.len()
+                    && l.iter()
+                        .zip(r.iter())
+                        .all(|((lk, lv), (rk, rv))| lk == rk && lv.equals_in(rv, seen));
+
+                seen.pop();
+
+                equal
+            }
+            (l, r) if l.to_f64().is_some() && r.to_f64().is_some() => {
+                l.compare(r).ok().flatten() == Some(Ordering::Equal)
+            }
+            (Value::Bool(l), Value::Bool(r)) => l == r,
+            (Value::Nil, Value::Nil) => true,
+            (Value::String(l), Value::String(r)) => l == r,
+            (Value::Closure(l), Value::Closure(r)) => l == r,
+            (Value::Native(l), Value::Native(r)) => l == r,
+            _ => false,
+        }
+    }
+
+    /*
+     * Only numbers, strings and lists have an order. Strings are ordered by their chars, and lists
+     * by their items in turn. Other values, or values of different types, can't be ordered. NaN
+     * can be ordered against numbers but is unordered, so every comparison with it is false.
+     */
+    pub(crate) fn compare(&self, other: &Value) -> Result<Option<Ordering>> {
+        self.compare_in(other, &mut vec![])
+    }
+
+    // Seen is as for equals, a pair of lists met again orders the same as it did before, which
+    // can only be equal as it's still being compared
+    fn compare_in(&self, other: &Value, seen: &mut Vec<Pair>) -> Result<Option<Ordering>> {
+        Ok(match (self, other) {
+            (Value::Int(l), Value::Int(r)) => Some(l.cmp(r)),
+            (Value::Int(l), Value::BigInt(r)) => Some(BigInt::from(*l).cmp(r)),
+            (Value::BigInt(l), Value::Int(r)) => Some(l.cmp(&BigInt::from(*r))),
+            (Value::BigInt(l), Value::BigInt(r)) => Some(l.cmp(r)),
+            // Converting the big int to a float would round it
+            (Value::BigInt(l), Value::Float(r)) => l.cmp_f64(*r),
+            (Value::Float(l), Value::BigInt(r)) => r.cmp_f64(*l).map(Ordering::reverse),
+            (Value::String(l), Value::String(r)) => Some(l.cmp(r)),
+            (Value::List(l), Value::List(r)) => {
+                let pair = pair(l, r);
+
+                if seen.contains(&pair) {
+                    return Ok(Some(Ordering::Equal));
+                }
+
+                seen.push(pair);
+
+                let ord = Value::compare_items(&l.borrow(), &r.borrow(), seen);
+
+                seen.pop();
+
+                ord?
+            }
+            (l, r) => match (l.to_f64(), r.to_f64()) {
+                (Some(l), Some(r)) => l.partial_cmp(&r),
+                _ => {
+                    return Err(ErrorKind::InvalidCompare {
+                        l: l.clone(),
+                        r: r.clone(),
+                    })
+                }
+            },
+        })
+    }
+
+    fn compare_items(l: &[Value], r: &[Value], seen: &mut Vec<Pair>) -> Result<Option<Ordering>> {
+        for (l, r) in l.iter().zip(r.iter()) {
+            match l.compare_in(r, seen)? {
+                Some(Ordering::Equal) => {}
+                ord => return Ok(ord),
+            }
+        }
+
+        Ok(Some(l.len().cmp(&r.len())))
+    }
+}
+
+// The addresses of two lists or maps being compared
+type Pair = (*const (), *const ());
+
+fn pair<T>(l: &Rc<T>, r: &Rc<T>) -> Pair {
+    (Rc::as_ptr(l) as *const (), Rc::as_ptr(r) as *const ())
+}
+
+impl Value {
+    // Strings are indexed by char, rather than by byte
+    pub(crate) fn index(&self, index: &Value) -> Result<Value> {
+        match self {
+            Value::String(string) => {
+                let index = Value::to_index(self, index, string.chars().count(), false)?;
+
+                Ok(Value::String(string.chars().skip(index).take(1).collect()))
+            }
+            Value::List(items) => {
+                let items = items.borrow();
+
+                let index = Value::to_index(self, index, items.len(), false)?;
+
+                Ok(items[index].clone())
+            }
+            Value::Map(entries) => {
+                let key = Value::to_key(self, index)?;
+
+                match entries.borrow().get(&key) {
+                    Some(val) => Ok(val.clone()),
+                    None => Err(ErrorKind::KeyNotFound(index.clone())),
+                }
+            }
+            _ => Err(ErrorKind::InvalidIndex {
+                val: self.clone(),
+                index: index.clone(),
+            }),
+        }
+    }
+
+    // Only lists and maps can be changed, strings are immutable. Setting a key which isn't in a
+    // map inserts it.
+    pub(crate) fn set_index(&self, index: &Value, item: Value) -> Result {
+        match self {
+            Value::List(items) => {
+                let mut items = items.borrow_mut();
+
+                let index = Value::to_index(self, index, items.len(), false)?;
+
+                items[index] = item;
+
+                Ok(())
+            }
+            Value::Map(entries) => {
+                let key = Value::to_key(self, index)?;
+
+                entries.borrow_mut().insert(key, item);
+
+                Ok(())
+            }
+            _ => Err(ErrorKind::InvalidIndex {
+                val: self.clone(),
+                index: index.clone(),
+            }),
+        }
+    }
+
+    // A missing bound slices from the start or to the end. Slicing a list copies the items into a
+    // new list.
+    pub(crate) fn slice(&self, start: Option<&Value>, end: Option<&Value>) -> Result<Value> {
+        match self {
+            Value::String(string) => {
+                let (start, count) = self.bounds(start, end, string.chars().count())?;
+
+                Ok(Value::String(
+                    string.chars().skip(start).take(count).collect(),
+                ))
+            }
+            Value::List(items) => {
+                let items = items.borrow();
+
+                let (start, count) = self.bounds(start, end, items.len())?;
+
+                Ok(Value::list(
+                    items.iter().skip(start).take(count).cloned().collect(),
+                ))
+            }
+            _ => Err(ErrorKind::InvalidIndex {
+                val: self.clone(),
+                index: start.or(end).cloned().unwrap_or(Value::Int(0)),
+            }),
+        }
+    }
+
+    // The start of a slice and the number of items in it
+    fn bounds(
+        &self,
+        start: Option<&Value>,
+        end: Option<&Value>,
+        len: usize,
+    ) -> Result<(usize, usize)> {
+        let start = match start {
+            Some(start) => Value::to_index(self, start, len, true)?,
+            None => 0,
+        };
+
+        let end = match end {
+            Some(end) => Value::to_index(self, end, len, true)?,
+            None => len,
+        };
+
+        // A start after the end gives an empty slice
+        Ok((start, end.saturating_sub(start)))
+    }
+
+    fn to_key(val: &Value, key: &Value) -> Result<MapKey> {
+        MapKey::new(key).ok_or_else(|| ErrorKind::InvalidIndex {
+            val: val.clone(),
+            index: key.clone(),
+        })
+    }
+
+    // Slice bounds can be one past the last item, indexes can't
+    fn to_index(val: &Value, index: &Value, len: usize, bound: bool) -> Result<usize> {
+        let limit = if bound { len + 1 } else { len };
+
+        match index {
+            Value::Int(i) if *i >= 0 && (*i as usize) < limit => Ok(*i as usize),
+            Value::Int(_) | Value::BigInt(_) => Err(ErrorKind::IndexOutOfBounds {
+                index: index.clone(),
+                len,
+            }),
+            _ => Err(ErrorKind::InvalidIndex {
+                val: val.clone(),
+                index: index.clone(),
+            }),
+        }
+    }
+}
+
+impl From<usize> for Value {
+    fn from(val: usize) -> Self {
+        Value::from_big(BigInt::from(val))
+    }
+}
+
+impl MapKey {
+    // None if the value can't be used as a key
+    pub(crate) fn new(key: &Value) -> Option<Self> {
+        match key {
+            Value::Int(key) => Some(MapKey::Int(BigInt::from(*key))),
+            Value::BigInt(key) => Some(MapKey::Int(key.clone())),
+            Value::String(key) => Some(MapKey::String(key.to_string())),
+            _ => None,
+        }
+    }
+}
+
+impl From<MapKey> for Value {
+    fn from(key: MapKey) -> Self {
+        match key {
+            MapKey::Int(key) => Value::from_big(key),
+            MapKey::String(key) => Value::String(key),
+        }
+    }
+}
+
+// Strings are quoted inside a list or map, so ["a, b"] and ["a", "b"] differ
+fn fmt_item(f: &mut fmt::Formatter<'_>, item: &Value, seen: &mut Vec<*const ()>) -> fmt::Result {
+    match item {
+        Value::String(item) => write!(f, "{:?}", item),
+        item => fmt_value(f, item, seen),
+    }
+}
+
+// Seen holds the lists and maps being printed, so one which contains itself is printed as [...]
+// or {...} the second time rather than forever
+fn fmt_value(f: &mut fmt::Formatter<'_>, val: &Value, seen: &mut Vec<*const ()>) -> fmt::Result {
+    match val {
+        Value::Int(val) => write!(f, "{}", val),
+        Value::BigInt(val) => write!(f, "{}", val),
+        // Debug always shows a fraction or exponent, so floats aren't mistaken for ints
+        Value::Float(val) => write!(f, "{:?}", val),
+        Value::Bool(val) => write!(f, "{}", val),
+        Value::Nil => write!(f, "nil"),
+        Value::String(val) => write!(f, "{}", val),
+        Value::List(items) => {
+            let ptr = Rc::as_ptr(items) as *const ();
+
+            if seen.contains(&ptr) {
+                return write!(f, "[...]");
+            }
+
+            seen.push(ptr);
+
+            write!(f, "[")?;
+
+            for (i, item) in items.borrow().iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+
+                fmt_item(f, item, seen)?;
+            }
+
+            seen.pop();
+
+            write!(f, "]")
+        }
+        Value::Map(entries) => {
+            let ptr = Rc::as_ptr(entries) as *const ();
+
+            if seen.contains(&ptr) {
+                return write!(f, "{{...}}");
+            }
+
+            seen.push(ptr);
+
+            write!(f, "{{")?;
+
+            for (i, (key, val)) in entries.borrow().iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+
+                fmt_item(f, &Value::from(key.clone()), seen)?;
+
+                write!(f, ": ")?;
+
+                fmt_item(f, val, seen)?;
+            }
+
+            seen.pop();
+
+            write!(f, "}}")
+        }
+        Value::Closure(closure) => write!(f, "<fn {}>", closure.function.name()),
+        Value::Native(native) => write!(f, "<native {}>", native.name),
+    }
+}
+
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Value::Int(val) => write!(f, "{}", val),
-            Value::Bool(val) => write!(f, "{}", val),
-            Value::String(val) => write!(f, "{}", val),
+        fmt_value(f, self, &mut vec![])
+    }
+}
+
+// Written out rather than derived, as a derived Debug would recurse forever on a list containing
+// itself. It's Display with strings quoted, so "1" and 1 can be told apart.
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_item(f, self, &mut vec![])
+    }
+}
+
+// Values are equal in Rust exactly when they are in scripts, so 1 == 1.0 and cycles are handled
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.equals(other)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cmp::Ordering, collections::BTreeMap};
+
+    use crate::{
+        testing::{eval, eval_err, string},
+        vm::ErrorKind as VmErrorKind,
+        Engine,
+    };
+
+    use super::{MapKey, Value};
+
+    fn ints(ints: &[i32]) -> Value {
+        Value::list(ints.iter().map(|i| Value::Int(*i)).collect())
+    }
+
+    // Pushes the item onto the list value
+    fn push(list: &Value, item: Value) {
+        match list {
+            Value::List(items) => items.borrow_mut().push(item),
+            _ => panic!("not a list"),
+        }
+    }
+
+    #[test]
+    fn displaying_lists_works() {
+        let xs = Value::list(vec![Value::Int(1), Value::String("a, b".to_string())]);
+
+        assert_eq!(xs.to_string(), r#"[1, "a, b"]"#);
+
+        let nested = Value::list(vec![xs.clone(), xs.clone()]);
+
+        // The same list twice isn't a cycle
+        assert_eq!(nested.to_string(), r#"[[1, "a, b"], [1, "a, b"]]"#);
+    }
+
+    #[test]
+    fn displaying_a_list_containing_itself_works() {
+        let xs = Value::list(vec![Value::Int(1)]);
+
+        push(&xs, xs.clone());
+
+        assert_eq!(xs.to_string(), "[1, [...]]");
+
+        let ys = Value::list(vec![xs.clone()]);
+
+        assert_eq!(ys.to_string(), "[[1, [...]]]");
+    }
+
+    #[test]
+    fn displaying_a_map_containing_itself_works() {
+        let m = Value::map(BTreeMap::new());
+
+        let key = MapKey::String("a".to_string());
+
+        if let Value::Map(entries) = &m {
+            entries.borrow_mut().insert(key, m.clone());
         }
+
+        assert_eq!(m.to_string(), r#"{"a": {...}}"#);
+
+        // A list and map containing each other
+        let xs = Value::list(vec![m.clone()]);
+
+        if let Value::Map(entries) = &m {
+            entries
+                .borrow_mut()
+                .insert(MapKey::Int(1.into()), xs.clone());
+        }
+
+        assert_eq!(xs.to_string(), r#"[{1: [...], "a": {...}}]"#);
+    }
+
+    #[test]
+    fn debugging_a_list_containing_itself_works() {
+        let mut engine = Engine::new().expect("creating engine failed");
+
+        let err = engine
+            .eval("let a = [1]\npush(a, a)\na + 1")
+            .expect_err("adding to a list should fail");
+
+        let debugged = format!("{:?}", err);
+
+        assert!(debugged.contains("l: [1, [...]]"), "{}", debugged);
+
+        assert_eq!(format!("{:?}", Value::list(vec![string("1")])), r#"["1"]"#);
+    }
+
+    #[test]
+    fn equality_in_rust_matches_scripts() {
+        assert_eq!(Value::Int(1), Value::Float(1.0));
+
+        assert_ne!(Value::Int(1), string("1"));
+
+        let (a, b) = (Value::list(vec![]), Value::list(vec![]));
+
+        push(&a, a.clone());
+
+        push(&b, b.clone());
+
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn comparing_lists_containing_each_other_works() {
+        let (a, b) = (
+            Value::list(vec![Value::Int(1)]),
+            Value::list(vec![Value::Int(1)]),
+        );
+
+        push(&a, b.clone());
+
+        push(&b, a.clone());
+
+        assert!(a.equals(&b));
+
+        assert_eq!(a.compare(&b).ok(), Some(Some(Ordering::Equal)));
+
+        let c = Value::list(vec![Value::Int(2)]);
+
+        push(&c, a.clone());
+
+        assert!(!a.equals(&c));
+
+        assert_eq!(a.compare(&c).ok(), Some(Some(Ordering::Less)));
+    }
+
+    #[test]
+    fn comparing_a_list_containing_itself_works() {
+        let xs = Value::list(vec![Value::Int(1)]);
+
+        push(&xs, xs.clone());
+
+        assert_eq!(xs.compare(&xs).ok(), Some(Some(Ordering::Equal)));
+
+        let ys = Value::list(vec![Value::Int(1)]);
+
+        push(&ys, ys.clone());
+
+        assert!(xs.equals(&ys));
+    }
+
+    #[test]
+    fn comparing_maps_containing_each_other_works() {
+        let (a, b) = (Value::map(BTreeMap::new()), Value::map(BTreeMap::new()));
+
+        for (map, other) in &[(&a, &b), (&b, &a)] {
+            if let Value::Map(entries) = map {
+                entries
+                    .borrow_mut()
+                    .insert(MapKey::String("x".to_string()), (*other).clone());
+            }
+        }
+
+        assert!(a.equals(&b));
+    }
+
+    #[test]
+    fn comparing_big_ints_with_floats_is_exact() {
+        let big = |s: &str| Value::BigInt(s.parse().expect("parsing failed"));
+
+        assert!(!big("99999999999999999999").equals(&Value::Float(1e20)));
+
+        assert!(big("100000000000000000000").equals(&Value::Float(1e20)));
+
+        assert_eq!(
+            Value::Float(1e20)
+                .compare(&big("99999999999999999999"))
+                .ok(),
+            Some(Some(Ordering::Greater))
+        );
+    }
+
+    #[test]
+    fn strings_are_indexed_by_char() {
+        let source = r#"
+            let s = "héllo" + ", " + "wörld"
+            s[1]
+        "#;
+
+        assert_eq!(eval(source), string("é"));
+    }
+
+    #[test]
+    fn slicing_strings_works() {
+        let source = r#"
+            let s = "héllo, wörld"
+            [s[7:], s[:5] + s[2:2]]
+        "#;
+
+        assert_eq!(
+            eval(source),
+            Value::list(vec![string("wörld"), string("héllo")])
+        );
+    }
+
+    #[test]
+    fn indexing_strings_fails_out_of_bounds() {
+        assert!(matches!(
+            eval_err(r#""ab"[2]"#),
+            VmErrorKind::IndexOutOfBounds { len: 2, .. }
+        ));
+
+        assert!(matches!(
+            eval_err(r#""ab"[:3]"#),
+            VmErrorKind::IndexOutOfBounds { len: 2, .. }
+        ));
+
+        assert!(matches!(
+            eval_err(r#""ab"[true]"#),
+            VmErrorKind::InvalidIndex { .. }
+        ));
+
+        assert!(matches!(eval_err("1[0]"), VmErrorKind::InvalidIndex { .. }));
+    }
+
+    #[test]
+    fn strings_are_immutable() {
+        let source = r#"
+            let s = "ab"
+            s[0] = "c"
+        "#;
+
+        assert!(matches!(eval_err(source), VmErrorKind::InvalidIndex { .. }));
+    }
+
+    #[test]
+    fn lists_are_shared() {
+        let source = r#"
+            let xs = [1, 2, 3]
+            let ys = xs
+            push(ys, 4)
+            xs[0] = xs[0] + 10
+            ys
+        "#;
+
+        assert_eq!(eval(source), ints(&[11, 2, 3, 4]));
+    }
+
+    #[test]
+    fn slicing_lists_copies_them() {
+        let source = r#"
+            let xs = [1, 2, 3]
+            let zs = xs[1:]
+            zs[0] = 0
+            [xs, zs]
+        "#;
+
+        assert_eq!(
+            eval(source),
+            Value::list(vec![ints(&[1, 2, 3]), ints(&[0, 3])])
+        );
+    }
+
+    #[test]
+    fn assigning_to_nested_lists_works() {
+        let source = r#"
+            let grid = [[1], [2]]
+            grid[1][0] = 5
+            grid
+        "#;
+
+        assert_eq!(eval(source).to_string(), "[[1], [5]]");
+    }
+
+    #[test]
+    fn indexing_lists_fails_out_of_bounds() {
+        let source = r#"
+            let xs = [1, 2]
+            xs[2] = 3
+        "#;
+
+        assert!(matches!(
+            eval_err(source),
+            VmErrorKind::IndexOutOfBounds { len: 2, .. }
+        ));
+
+        assert!(matches!(
+            eval_err("[][0]"),
+            VmErrorKind::IndexOutOfBounds { len: 0, .. }
+        ));
+    }
+
+    #[test]
+    fn maps_are_shared() {
+        let source = r#"
+            let config = {
+                "name": "inter",
+                1: [2],
+            }
+            let alias = config
+            alias["debug"] = true
+            config[1][0] = 3
+            config
+        "#;
+
+        // Int keys are ordered before string keys
+        assert_eq!(
+            eval(source).to_string(),
+            r#"{1: [3], "debug": true, "name": "inter"}"#
+        );
+    }
+
+    #[test]
+    fn repeated_keys_keep_their_last_value() {
+        let source = r#"
+            let counts = {"a": 1, "b": 2, "a": 3}
+            [counts["a"], len(counts)]
+        "#;
+
+        assert_eq!(eval(source), ints(&[3, 2]));
+    }
+
+    #[test]
+    fn ints_and_big_ints_are_the_same_key() {
+        let source = r#"
+            let m = {2147483647 + 1: "big"}
+            m[2147483648]
+        "#;
+
+        assert_eq!(eval(source), string("big"));
+    }
+
+    #[test]
+    fn looking_up_missing_keys_fails() {
+        assert!(matches!(
+            eval_err(r#"{"a": 1}["b"]"#),
+            VmErrorKind::KeyNotFound(Value::String(key)) if key == "b"
+        ));
+
+        assert!(matches!(
+            eval_err("{1.5: 1}"),
+            VmErrorKind::InvalidIndex { .. }
+        ));
+
+        assert!(matches!(
+            eval_err("{}[[]]"),
+            VmErrorKind::InvalidIndex { .. }
+        ));
+    }
+
+    #[test]
+    fn testing_equality_works() {
+        for (source, equal) in &[
+            ("1 == 1.0", true),
+            (r#"1 == "1""#, false),
+            ("nil != false", true),
+            ("[1, [2]] == [1.0, [2]]", true),
+            ("[1, 2] == [1]", false),
+            (r#"{"k": [1]} == {"k": [1]}"#, true),
+            (r#"{"k": 1} == {"j": 1}"#, false),
+            ("len == len", true),
+            ("len == push", false),
+        ] {
+            assert_eq!(eval(source), Value::Bool(*equal), "{}", source);
+        }
+    }
+
+    #[test]
+    fn ordering_values_works() {
+        for (source, ordered) in &[
+            ("[1, 2] < [1, 3]", true),
+            ("[1] < [1, 0]", true),
+            (r#""b" > "abc""#, true),
+            (r#""abc" < "abd""#, true),
+            ("0.0 / 1 < 99999999999999999999", true),
+            ("2.5 > 2", true),
+        ] {
+            assert_eq!(eval(source), Value::Bool(*ordered), "{}", source);
+        }
+    }
+
+    #[test]
+    fn ordering_different_types_fails() {
+        for source in &[
+            "5 < true",
+            r#"1 < "1""#,
+            "nil >= nil",
+            "true > false",
+            "{} < {}",
+            r#"[1, 2] < [1, "2"]"#,
+        ] {
+            assert!(
+                matches!(eval_err(source), VmErrorKind::InvalidCompare { .. }),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn ordering_lists_stops_at_the_first_difference() {
+        // The rest of the items aren't compared, so their types don't matter
+        assert_eq!(eval(r#"[1, "a"] < [2, nil]"#), Value::Bool(true));
     }
 }